@@ -0,0 +1,54 @@
+//! Renders the canned scenes offscreen and compares them against the images in `golden/`.
+//!
+//! ```sh
+//! cargo run --example golden --features offscreen            # compare
+//! cargo run --example golden --features offscreen -- --update # regenerate the golden images
+//! ```
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use xplm_egui::offscreen::{self, GoldenResult};
+
+fn main() -> Result<()> {
+    let update = std::env::args().any(|arg| arg == "--update");
+    let golden_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden");
+    std::fs::create_dir_all(&golden_dir)?;
+
+    let mut failures = 0;
+    for check in offscreen::check_goldens(&golden_dir, update)? {
+        let path = check.path.display();
+        match check.result {
+            GoldenResult::Match(_) => println!("ok       {path}"),
+            GoldenResult::Updated => println!("updated  {path}"),
+            GoldenResult::Missing => {
+                println!("missing  {path} (run with --update)");
+                failures += 1;
+            }
+            GoldenResult::Mismatch(diff) => {
+                let actual_path = check.save_actual()?;
+                println!(
+                    "MISMATCH {path}: {} pixels differ by up to {}, see {}",
+                    diff.differing_pixels,
+                    diff.max_difference,
+                    actual_path.display()
+                );
+                failures += 1;
+            }
+            GoldenResult::SizeMismatch { expected } => {
+                let actual_path = check.save_actual()?;
+                println!(
+                    "MISMATCH {path}: golden image is {}x{}, see {}",
+                    expected.0,
+                    expected.1,
+                    actual_path.display()
+                );
+                failures += 1;
+            }
+        }
+    }
+    if failures > 0 {
+        bail!("{failures} golden image(s) failed");
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use xplm::{data::DataRead, geometry::Rect};
+
+use crate::misc_util::LazyDataRef;
+
+/// The parts of an X-Plane window the gui interacts with.
+///
+/// Implemented for [`crate::XplmWindow`] and [`xplm::window::Window`], and by `mock::MockWindow` for tests.
+pub trait WindowBackend {
+    /// The window geometry in X-Plane's global boxel coordinates, with the origin in the bottom left.
+    fn geometry(&self) -> Rect<i32>;
+    fn take_keyboard_focus(&self);
+    fn release_keyboard_focus(&self);
+}
+
+impl WindowBackend for xplm::window::Window {
+    fn geometry(&self) -> Rect<i32> {
+        xplm::window::Window::geometry(self)
+    }
+
+    fn take_keyboard_focus(&self) {
+        xplm::window::Window::take_keyboard_focus(self)
+    }
+
+    fn release_keyboard_focus(&self) {
+        self.loose_keyboard_focus()
+    }
+}
+
+impl WindowBackend for crate::XplmWindow {
+    fn geometry(&self) -> Rect<i32> {
+        crate::XplmWindow::geometry(self)
+    }
+
+    fn take_keyboard_focus(&self) {
+        crate::XplmWindow::take_keyboard_focus(self)
+    }
+
+    fn release_keyboard_focus(&self) {
+        crate::XplmWindow::release_keyboard_focus(self)
+    }
+}
+
+/// The parts of the sim the gui interacts with, apart from its window.
+///
+/// [`XplmBackend`] talks to X-Plane, `mock::MockBackend` stands in for it in tests.
+pub trait SimBackend {
+    /// X-Plane's user interface scale, i.e. framebuffer pixels per boxel, if known.
+    fn ui_scale(&mut self) -> Option<f32>;
+    /// Prepares X-Plane's graphics state for painting the ui.
+    fn set_graphics_state(&self);
+    /// Writes a line of the crate's log, e.g. about OpenGL errors.
+    ///
+    /// The backend of the last created context logs for all of them.
+    fn log(message: &str)
+    where
+        Self: Sized;
+}
+
+/// The [`SimBackend`] of a plugin running inside X-Plane.
+pub struct XplmBackend {
+    ui_scale: LazyDataRef<f32>,
+}
+
+impl Default for XplmBackend {
+    fn default() -> Self {
+        Self {
+            ui_scale: LazyDataRef::new("sim/graphics/misc/user_interface_scale"),
+        }
+    }
+}
+
+impl SimBackend for XplmBackend {
+    fn ui_scale(&mut self) -> Option<f32> {
+        self.ui_scale.get().map(|scale| scale.get())
+    }
+
+    fn set_graphics_state(&self) {
+        xplm::draw::set_state(&xplm::draw::GraphicsState {
+            fog: false,
+            lighting: false,
+            alpha_testing: true,
+            alpha_blending: true,
+            depth_testing: false,
+            depth_writing: false,
+            textures: 1, // TODO is this right?
+        });
+    }
+
+    fn log(message: &str) {
+        xplm::debug(format!("{message}\n"));
+    }
+}
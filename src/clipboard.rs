@@ -0,0 +1,82 @@
+/// A place to exchange text with other applications, used for copy, cut and paste in text edits.
+///
+/// Set a custom implementation with [`crate::XplmGuiContext::set_clipboard`].
+pub trait Clipboard {
+    /// Returns the current text contents, if any.
+    fn get(&mut self) -> Option<String>;
+    /// Replaces the contents with `text`.
+    fn set(&mut self, text: String);
+}
+
+/// A clipboard that only lives inside the plugin.
+///
+/// This is the fallback if the system clipboard is not available.
+#[derive(Debug, Default)]
+pub struct MemoryClipboard {
+    contents: Option<String>,
+}
+
+impl Clipboard for MemoryClipboard {
+    fn get(&mut self) -> Option<String> {
+        self.contents.clone()
+    }
+
+    fn set(&mut self, text: String) {
+        self.contents = Some(text);
+    }
+}
+
+/// The clipboard of the operating system.
+///
+/// On Linux, this talks to the X11 clipboard. X-Plane is an X11 application,
+/// so on Wayland sessions this reaches the desktop clipboard through XWayland.
+#[cfg(feature = "clipboard")]
+pub struct SystemClipboard {
+    context: copypasta::ClipboardContext,
+}
+
+#[cfg(feature = "clipboard")]
+impl SystemClipboard {
+    /// Connects to the system clipboard, returns `None` if that is not possible.
+    pub fn new() -> Option<Self> {
+        match copypasta::ClipboardContext::new() {
+            Ok(context) => Some(Self { context }),
+            Err(err) => {
+                log!("egui: failed to initialize the system clipboard: {err}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(feature = "clipboard")]
+impl Clipboard for SystemClipboard {
+    fn get(&mut self) -> Option<String> {
+        use copypasta::ClipboardProvider as _;
+        match self.context.get_contents() {
+            Ok(contents) => Some(contents),
+            Err(err) => {
+                log!("egui: failed to read from the clipboard: {err}");
+                None
+            }
+        }
+    }
+
+    fn set(&mut self, text: String) {
+        use copypasta::ClipboardProvider as _;
+        if let Err(err) = self.context.set_contents(text) {
+            log!("egui: failed to write to the clipboard: {err}");
+        }
+    }
+}
+
+/// The system clipboard if available, otherwise a [`MemoryClipboard`].
+pub(crate) fn default_clipboard() -> Box<dyn Clipboard> {
+    #[cfg(feature = "clipboard")]
+    {
+        if let Some(clipboard) = SystemClipboard::new() {
+            return Box::new(clipboard);
+        }
+    }
+    Box::new(MemoryClipboard::default())
+}
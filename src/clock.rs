@@ -0,0 +1,79 @@
+use std::time::Instant;
+
+use xplm::data::{borrowed::DataRef, DataRead};
+
+use crate::Error;
+
+/// The time source for [`egui::RawInput::time`], which drives animations, tooltip delays
+/// and double click detection.
+///
+/// Set a clock with [`crate::XplmGuiContext::set_clock`].
+pub trait Clock {
+    /// Seconds since some arbitrary, but fixed point in time. Must never decrease.
+    fn now(&mut self) -> f64;
+}
+
+/// Real time, which keeps running while the sim is paused. This is the default.
+#[derive(Debug)]
+pub struct RealTimeClock {
+    start_time: Instant,
+}
+
+impl Default for RealTimeClock {
+    fn default() -> Self {
+        Self {
+            start_time: Instant::now(),
+        }
+    }
+}
+
+impl Clock for RealTimeClock {
+    fn now(&mut self) -> f64 {
+        self.start_time.elapsed().as_secs_f64()
+    }
+}
+
+/// X-Plane's sim time, which stands still while the sim is paused, and with it all egui animations.
+pub struct SimTimeClock {
+    running_time: DataRef<f32>,
+    last_time: f64,
+}
+
+impl SimTimeClock {
+    pub fn new() -> Result<Self, Error> {
+        const NAME: &str = "sim/time/total_running_time_sec";
+        Ok(Self {
+            running_time: DataRef::find(NAME).map_err(|_| Error::MissingDataRef(NAME))?,
+            last_time: 0.0,
+        })
+    }
+}
+
+impl Clock for SimTimeClock {
+    fn now(&mut self) -> f64 {
+        // Guard against the dataref jumping back, e.g. when the sim is reset
+        never_decreasing(&mut self.last_time, self.running_time.get() as f64)
+    }
+}
+
+/// Returns `time`, or `last_time` if `time` lies before it, and remembers the result in `last_time`.
+fn never_decreasing(last_time: &mut f64, time: f64) -> f64 {
+    *last_time = last_time.max(time);
+    *last_time
+}
+
+#[cfg(test)]
+mod tests {
+    use super::never_decreasing;
+
+    #[test]
+    fn sim_time_never_decreases() {
+        let mut last_time = 0.0;
+        assert_eq!(never_decreasing(&mut last_time, 10.0), 10.0);
+        assert_eq!(never_decreasing(&mut last_time, 12.5), 12.5);
+        // The sim was reset
+        assert_eq!(never_decreasing(&mut last_time, 0.5), 12.5);
+        assert_eq!(never_decreasing(&mut last_time, 12.0), 12.5);
+        assert_eq!(never_decreasing(&mut last_time, 13.0), 13.0);
+    }
+}
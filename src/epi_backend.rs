@@ -0,0 +1,223 @@
+//! Runs the [`epi::App`]s written for eframe in X-Plane windows.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use xplm::{geometry::Rect, window::WindowOptions};
+
+use crate::{input::XplmInputState, Error, GuiDelegate, XplmGuiContext, XplmWindow, XplmWindowRef};
+
+/// Remembers repaint requests from other threads until the next frame.
+#[derive(Default)]
+struct RepaintSignal(AtomicBool);
+
+impl epi::backend::RepaintSignal for RepaintSignal {
+    fn request_repaint(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// A [`GuiDelegate`] that runs an [`epi::App`], like [`crate::EguiWindow`] does for [`crate::App`]s.
+///
+/// Of the [`epi::Frame`] services, textures, repaint requests, quitting and window sizes are supported.
+/// Quitting hides the window, after the app was saved and its `on_exit` was called.
+/// Window titles, decorations and dragging are up to X-Plane and are ignored.
+pub struct EpiWindow<A: epi::App> {
+    gui: XplmGuiContext,
+    app: A,
+    frame: epi::Frame,
+    repaint_signal: Arc<RepaintSignal>,
+    storage: Option<Box<dyn epi::Storage>>,
+    last_auto_save: Instant,
+    set_up: bool,
+    quit: bool,
+}
+
+impl<A: epi::App> EpiWindow<A> {
+    pub fn new(app: A) -> Result<Self, Error> {
+        Ok(Self::with_context(XplmGuiContext::new()?, app))
+    }
+
+    /// Like [`EpiWindow::new`], but with a context that was e.g. created with [`XplmGuiContext::with_options`].
+    pub fn with_context(gui: XplmGuiContext, app: A) -> Self {
+        let repaint_signal = Arc::new(RepaintSignal::default());
+        let frame = epi::Frame::new(epi::backend::FrameData {
+            info: epi::IntegrationInfo {
+                name: "xplm-egui",
+                web_info: None,
+                prefer_dark_mode: None,
+                cpu_usage: None,
+                native_pixels_per_point: Some(gui.pixels_per_point()),
+            },
+            output: Default::default(),
+            repaint_signal: repaint_signal.clone(),
+        });
+        Self {
+            gui,
+            app,
+            frame,
+            repaint_signal,
+            storage: None,
+            last_auto_save: Instant::now(),
+            set_up: false,
+            quit: false,
+        }
+    }
+
+    /// Passes `storage` to [`epi::App::setup`] and saves the app in it every
+    /// [`epi::App::auto_save_interval`] and on exit. Without storage, the app isn't saved.
+    pub fn with_storage(mut self, storage: impl epi::Storage + 'static) -> Self {
+        self.storage = Some(Box::new(storage));
+        self
+    }
+
+    pub fn app(&self) -> &A {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut A {
+        &mut self.app
+    }
+
+    /// The context, to change e.g. the scale or the clock.
+    pub fn gui(&self) -> &XplmGuiContext {
+        &self.gui
+    }
+
+    pub fn gui_mut(&mut self) -> &mut XplmGuiContext {
+        &mut self.gui
+    }
+
+    /// Whether the app quit through [`epi::Frame::quit`].
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+
+    fn run_frame(&mut self, window: &XplmWindow) {
+        if !self.set_up {
+            self.set_up = true;
+            self.app
+                .setup(self.gui.ctx(), &self.frame, self.storage.as_deref());
+        }
+        if self.repaint_signal.0.swap(false, Ordering::Relaxed) {
+            self.gui.request_repaint();
+        }
+        self.gui
+            .set_max_size_points(Some(self.app.max_size_points()));
+        self.frame.lock().info.native_pixels_per_point = Some(self.gui.pixels_per_point());
+
+        let clear_color = egui::Color32::from(self.app.clear_color());
+        let (app, frame) = (&mut self.app, &self.frame);
+        self.gui.update(window, |ctx| {
+            // X-Plane doesn't clear the window for us, so the clear color is painted below the ui
+            if clear_color.a() > 0 {
+                ctx.layer_painter(egui::LayerId::background()).rect_filled(
+                    ctx.input().screen_rect(),
+                    0.0,
+                    clear_color,
+                );
+            }
+            app.update(ctx, frame);
+        });
+
+        let output = std::mem::take(&mut self.frame.lock().output);
+        self.handle_output(window, output);
+        if self.quit {
+            return;
+        }
+        self.auto_save();
+        self.gui.draw(window);
+    }
+
+    fn handle_output(&mut self, window: &XplmWindow, output: epi::backend::AppOutput) {
+        let textures = output.tex_allocation_data;
+        let gui = &mut self.gui;
+        for (id, image) in textures.creations {
+            let result = gui
+                .painter
+                .set_user_texture(&gui.gl, id, image.size, &image.pixels);
+            if let Err(err) = result {
+                log!("egui: failed to allocate texture for epi: {err}");
+            }
+        }
+        for id in textures.destructions {
+            gui.painter.free_texture(id);
+        }
+
+        if let Some(size) = output.window_size {
+            // Keep the top left corner in place
+            let geometry = window.geometry();
+            let boxels_per_point = self.gui.pixels_per_point() / self.gui.ui_scale();
+            window.set_geometry(Rect::from_left_top_right_bottom(
+                geometry.left(),
+                geometry.top(),
+                geometry.left() + (size.x * boxels_per_point).round() as i32,
+                geometry.top() - (size.y * boxels_per_point).round() as i32,
+            ));
+        }
+
+        if output.quit {
+            self.exit();
+            window.set_visible(false);
+        }
+    }
+
+    fn auto_save(&mut self) {
+        if self.last_auto_save.elapsed() >= self.app.auto_save_interval() {
+            self.save();
+        }
+    }
+
+    fn save(&mut self) {
+        if let Some(storage) = &mut self.storage {
+            self.app.save(storage.as_mut());
+            storage.flush();
+        }
+        self.last_auto_save = Instant::now();
+    }
+
+    /// Saves the app, lets it clean up and releases the OpenGL resources of the ui.
+    fn exit(&mut self) {
+        if self.quit {
+            return;
+        }
+        self.quit = true;
+        self.save();
+        self.app.on_exit();
+        self.gui.shutdown();
+    }
+}
+
+impl<A: epi::App + 'static> EpiWindow<A> {
+    /// Creates an X-Plane window running `app`.
+    pub fn create(
+        geometry: Rect<i32>,
+        options: WindowOptions,
+        app: A,
+    ) -> Result<XplmWindowRef, Error> {
+        Ok(XplmWindow::create(geometry, Self::new(app)?, options))
+    }
+}
+
+impl<A: epi::App> Drop for EpiWindow<A> {
+    fn drop(&mut self) {
+        self.exit();
+    }
+}
+
+impl<A: epi::App + 'static> GuiDelegate for EpiWindow<A> {
+    fn draw(&mut self, window: &XplmWindow) {
+        if !self.quit {
+            self.run_frame(window);
+        }
+    }
+
+    fn input_state(&mut self) -> &mut XplmInputState {
+        &mut self.gui.input_state
+    }
+}
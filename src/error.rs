@@ -0,0 +1,129 @@
+use std::fmt;
+
+/// A shader stage of the egui program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+impl ShaderStage {
+    pub(crate) fn gl_type(self) -> u32 {
+        match self {
+            ShaderStage::Vertex => glow::VERTEX_SHADER,
+            ShaderStage::Fragment => glow::FRAGMENT_SHADER,
+        }
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => f.write_str("vertex"),
+            ShaderStage::Fragment => f.write_str("fragment"),
+        }
+    }
+}
+
+/// Everything that can go wrong in this crate.
+#[derive(Debug)]
+pub enum Error {
+    /// OpenGL refused to create a shader of the given stage.
+    ShaderCreation { stage: ShaderStage, err: String },
+    /// A shader failed to compile, with the driver's info log.
+    ShaderCompile { stage: ShaderStage, log: String },
+    /// OpenGL refused to create the shader program.
+    ProgramCreation(String),
+    /// The shader program failed to link, with the driver's info log.
+    ProgramLink(String),
+    /// The linked program lacks a uniform or attribute of the given name.
+    MissingShaderInput(&'static str),
+    /// OpenGL refused to create a buffer.
+    BufferCreation(String),
+    /// OpenGL refused to create a vertex array.
+    VertexArrayCreation(String),
+    /// OpenGL refused to create a texture.
+    TextureCreation(String),
+    /// A texture or texture update with a width or height of zero.
+    TextureSize([usize; 2]),
+    /// The pixel data doesn't match the size of a texture (update), `len` is in bytes.
+    TextureDataLength { size: [usize; 2], len: usize },
+    /// A texture update reaches beyond the texture of `texture_size`.
+    TextureRegion {
+        pos: [usize; 2],
+        size: [usize; 2],
+        texture_size: [usize; 2],
+    },
+    /// The id doesn't belong to a user texture of this context.
+    UnknownTexture(egui::TextureId),
+    /// An OpenGL error reported by `glGetError`, see [`crate::check_gl_error`].
+    Gl { description: String, code: u32 },
+    /// A message from the OpenGL debug output, see [`crate::XplmGuiContext::enable_gl_debug_output`].
+    GlDebugMessage {
+        source: u32,
+        kind: u32,
+        id: u32,
+        severity: u32,
+        message: String,
+    },
+    /// A dataref that is required could not be found.
+    MissingDataRef(&'static str),
+    /// An offscreen OpenGL context could not be created, see [`crate::offscreen`].
+    ContextCreation(String),
+    /// A golden image could not be read or written, see [`crate::offscreen::check_goldens`].
+    GoldenImage(String),
+    /// An input recording could not be written or read, see [`crate::recording`].
+    Recording(String),
+    /// The OpenGL resources were already released by [`crate::XplmGuiContext::shutdown`].
+    ShutDown,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ShaderCreation { stage, err } => {
+                write!(f, "failed to create {stage} shader: {err}")
+            }
+            Error::ShaderCompile { stage, log } => {
+                write!(f, "failed to compile {stage} shader: {log}")
+            }
+            Error::ProgramCreation(err) => write!(f, "failed to create shader program: {err}"),
+            Error::ProgramLink(log) => write!(f, "failed to link shader program: {log}"),
+            Error::MissingShaderInput(name) => write!(f, "shader program has no input {name}"),
+            Error::BufferCreation(err) => write!(f, "failed to create buffer: {err}"),
+            Error::VertexArrayCreation(err) => write!(f, "failed to create vertex array: {err}"),
+            Error::TextureCreation(err) => write!(f, "failed to create texture: {err}"),
+            Error::TextureSize([w, h]) => write!(f, "texture size {w}x{h} is empty"),
+            Error::TextureDataLength { size: [w, h], len } => {
+                write!(f, "{len} bytes of pixel data don't fit a size of {w}x{h}")
+            }
+            Error::TextureRegion {
+                pos: [x, y],
+                size: [w, h],
+                texture_size: [tw, th],
+            } => write!(
+                f,
+                "region of {w}x{h} at ({x}, {y}) doesn't fit into the {tw}x{th} texture"
+            ),
+            Error::UnknownTexture(id) => write!(f, "unknown user texture {id:?}"),
+            Error::Gl { description, code } => write!(f, "gl error {description}: {code:x}"),
+            Error::GlDebugMessage {
+                source,
+                kind,
+                id,
+                severity,
+                message,
+            } => write!(
+                f,
+                "gl debug message (source {source:x}, type {kind:x}, id {id}, severity {severity:x}): {message}"
+            ),
+            Error::MissingDataRef(name) => write!(f, "dataref {name} not found"),
+            Error::ContextCreation(err) => write!(f, "failed to create OpenGL context: {err}"),
+            Error::GoldenImage(err) => write!(f, "failed to access golden image: {err}"),
+            Error::Recording(err) => write!(f, "input recording failed: {err}"),
+            Error::ShutDown => f.write_str("the gui context was shut down"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
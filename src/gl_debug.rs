@@ -0,0 +1,149 @@
+//! The OpenGL debug output used by [`crate::XplmGuiContext::enable_gl_debug_output`].
+//!
+//! glow's `debug_message_callback` can't be used: it passes a pointer to a stack variable as the
+//! user parameter and offers no way to get the previous callback back. X-Plane and other plugins
+//! share the OpenGL context with us, so the callback is called through raw function pointers and
+//! only installed while egui paints.
+
+#![allow(unsafe_code)]
+
+use std::{
+    ffi::{c_char, c_void, CStr},
+    ptr,
+};
+
+use glow::HasContext;
+
+use crate::{misc_util, Error};
+
+type DebugProc = extern "system" fn(
+    source: u32,
+    kind: u32,
+    id: u32,
+    severity: u32,
+    length: i32,
+    message: *const c_char,
+    user_param: *mut c_void,
+);
+type DebugMessageCallbackFn =
+    unsafe extern "system" fn(callback: Option<DebugProc>, user_param: *const c_void);
+type GetPointervFn = unsafe extern "system" fn(pname: u32, params: *mut *mut c_void);
+
+/// The entry points of `KHR_debug` or `ARB_debug_output`.
+pub(crate) struct GlDebugOutput {
+    debug_message_callback: DebugMessageCallbackFn,
+    get_pointerv: GetPointervFn,
+    /// `ARB_debug_output` has no `GL_DEBUG_OUTPUT` switch, it is always on in debug contexts.
+    has_switch: bool,
+}
+
+/// The debug output state of X-Plane or whoever set it up before us.
+pub(crate) struct PreviousDebugState {
+    callback: *mut c_void,
+    user_param: *mut c_void,
+    enabled: bool,
+    synchronous: bool,
+}
+
+impl GlDebugOutput {
+    /// Loads the entry points with the same loader as [`crate::init_gl_context`].
+    ///
+    /// Returns `None` if the current context supports neither extension.
+    pub(crate) fn load(gl: &glow::Context) -> Option<Self> {
+        let extensions = gl.supported_extensions();
+        let (name, has_switch) = if extensions.contains("GL_KHR_debug") {
+            ("glDebugMessageCallback", true)
+        } else if extensions.contains("GL_ARB_debug_output") {
+            ("glDebugMessageCallbackARB", false)
+        } else {
+            return None;
+        };
+        let debug_message_callback = gl_loader::get_proc_address(name);
+        let get_pointerv = gl_loader::get_proc_address("glGetPointerv");
+        if debug_message_callback.is_null() || get_pointerv.is_null() {
+            return None;
+        }
+        // SAFETY: the loader returned the entry points of these names, which have these signatures
+        unsafe {
+            Some(Self {
+                debug_message_callback: std::mem::transmute::<*const (), DebugMessageCallbackFn>(
+                    debug_message_callback,
+                ),
+                get_pointerv: std::mem::transmute::<*const (), GetPointervFn>(get_pointerv),
+                has_switch,
+            })
+        }
+    }
+
+    /// Routes debug messages to [`misc_util::report_gl_error`] until [`GlDebugOutput::uninstall`] is called
+    /// with the returned state.
+    pub(crate) unsafe fn install(&self, gl: &glow::Context) -> PreviousDebugState {
+        let mut callback = ptr::null_mut();
+        let mut user_param = ptr::null_mut();
+        (self.get_pointerv)(glow::DEBUG_CALLBACK_FUNCTION, &mut callback);
+        (self.get_pointerv)(glow::DEBUG_CALLBACK_USER_PARAM, &mut user_param);
+        let previous = PreviousDebugState {
+            callback,
+            user_param,
+            enabled: self.has_switch && gl.is_enabled(glow::DEBUG_OUTPUT),
+            synchronous: gl.is_enabled(glow::DEBUG_OUTPUT_SYNCHRONOUS),
+        };
+
+        // Synchronous, so that the callback runs on this thread while the failing call is on the stack
+        gl.enable(glow::DEBUG_OUTPUT_SYNCHRONOUS);
+        if self.has_switch {
+            gl.enable(glow::DEBUG_OUTPUT);
+        }
+        (self.debug_message_callback)(Some(debug_callback), ptr::null());
+        misc_util::set_debug_output_installed(true);
+        previous
+    }
+
+    /// Puts back the callback and switches that were set before [`GlDebugOutput::install`].
+    ///
+    /// Panics afterwards if a debug message was reported with [`crate::GlErrorPolicy::Panic`].
+    pub(crate) unsafe fn uninstall(&self, gl: &glow::Context, previous: PreviousDebugState) {
+        // SAFETY: the pointer was a callback of this signature, or null
+        let callback = std::mem::transmute::<*mut c_void, Option<DebugProc>>(previous.callback);
+        (self.debug_message_callback)(callback, previous.user_param);
+        if self.has_switch && !previous.enabled {
+            gl.disable(glow::DEBUG_OUTPUT);
+        }
+        if !previous.synchronous {
+            gl.disable(glow::DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+        misc_util::set_debug_output_installed(false);
+        // Only now that X-Plane's callback is back
+        misc_util::panic_on_deferred_gl_error();
+    }
+}
+
+extern "system" fn debug_callback(
+    source: u32,
+    kind: u32,
+    id: u32,
+    severity: u32,
+    length: i32,
+    message: *const c_char,
+    _user_param: *mut c_void,
+) {
+    if severity == glow::DEBUG_SEVERITY_NOTIFICATION || message.is_null() {
+        return;
+    }
+    // SAFETY: OpenGL passes a valid message of `length` bytes, or nul-terminated if `length` is negative
+    let message = unsafe {
+        match usize::try_from(length) {
+            Ok(length) => {
+                String::from_utf8_lossy(std::slice::from_raw_parts(message.cast(), length))
+            }
+            Err(_) => CStr::from_ptr(message).to_string_lossy(),
+        }
+    };
+    misc_util::report_gl_debug_message(Error::GlDebugMessage {
+        source,
+        kind,
+        id,
+        severity,
+        message: message.into_owned(),
+    });
+}
@@ -1,49 +1,277 @@
 use egui::{Modifiers, Pos2, Rect};
 
-#[derive(Default)]
+use xplm::{
+    geometry::Point,
+    window::{Key, MouseAction},
+};
+
+#[cfg(feature = "recording")]
+use crate::recording::{InputRecorder, RawEvent};
+use crate::{
+    backend::WindowBackend,
+    clipboard::{default_clipboard, Clipboard},
+    clock::{Clock, RealTimeClock},
+};
+
 pub struct XplmInputState {
-    //start_time: instant::Instant,
+    clock: Box<dyn Clock>,
+    /// The time of the last frame according to `clock`.
+    last_frame_time: Option<f64>,
     egui_input: egui::RawInput,
-    //pointer_pos_in_points: Option<egui::Pos2>,
+    /// Whether the pointer was above an egui area during the last frame.
+    pointer_over_area: bool,
+    /// Whether egui was interested in the pointer during the last frame.
+    wants_pointer_input: bool,
+    /// Whether the click that started the current drag was consumed, indexed by [`egui::PointerButton`].
+    pointer_captured: [bool; 3],
+    click_through: bool,
+    scroll_points_per_line: f32,
+    shift_scrolls_horizontally: bool,
+    pointer_pos_in_points: Option<egui::Pos2>,
+    /// The screen rect egui got during the last frame.
+    last_screen_rect: Option<Rect>,
     //any_pointer_button_down: bool,
-    //current_cursor_icon: egui::CursorIcon,
-    //current_pixels_per_point: f32,
+    current_cursor_icon: egui::CursorIcon,
+    /// Whether the cursor is drawn by us instead of X-Plane.
+    custom_cursor: bool,
+    current_pixels_per_point: f32,
+    /// Window coordinates are in boxels, see [`XplmInputState::set_scale`].
+    points_per_boxel: f32,
 
-    //clipboard: clipboard::Clipboard,
+    clipboard: Box<dyn Clipboard>,
+    #[cfg(feature = "recording")]
+    recorder: Option<InputRecorder>,
+    /// Used instead of the live input for the next frame, see [`crate::XplmGuiContext::replay_frame`].
+    #[cfg(feature = "recording")]
+    replayed_input: Option<egui::RawInput>,
     //screen_reader: screen_reader::ScreenReader,
 }
 
+impl Default for XplmInputState {
+    fn default() -> Self {
+        Self {
+            egui_input: Default::default(),
+            clock: Box::new(RealTimeClock::default()),
+            last_frame_time: None,
+            pointer_pos_in_points: None,
+            last_screen_rect: None,
+            current_pixels_per_point: 1.0,
+            points_per_boxel: 1.0,
+            pointer_over_area: false,
+            wants_pointer_input: false,
+            pointer_captured: [false; 3],
+            click_through: false,
+            scroll_points_per_line: 50.0,
+            shift_scrolls_horizontally: true,
+            clipboard: default_clipboard(),
+            current_cursor_icon: egui::CursorIcon::Default,
+            custom_cursor: false,
+            #[cfg(feature = "recording")]
+            recorder: None,
+            #[cfg(feature = "recording")]
+            replayed_input: None,
+        }
+    }
+}
+
 impl XplmInputState {
     pub fn take_egui_input(&mut self) -> egui::RawInput {
-        self.egui_input.take()
+        #[cfg(feature = "recording")]
+        {
+            if let Some(input) = self.replayed_input.take() {
+                // The live input is dropped, it would make the replay nondeterministic
+                self.egui_input.take();
+                return input;
+            }
+        }
+        let time = self.clock.now();
+        if let Some(last_frame_time) = self.last_frame_time {
+            // The last frame time is our best guess for the next one
+            self.egui_input.predicted_dt = ((time - last_frame_time) as f32).clamp(0.0, 0.1);
+        }
+        self.last_frame_time = Some(time);
+        self.egui_input.time = Some(time);
+        self.egui_input.pixels_per_point = Some(self.current_pixels_per_point);
+        self.last_screen_rect = self.egui_input.screen_rect;
+        let input = self.egui_input.take();
+        #[cfg(feature = "recording")]
+        self.record_frame(&input);
+        input
+    }
+
+    /// Starts writing every frame's input, along with the XPLM events it was made from, to `path`,
+    /// so that it can be replayed with [`crate::XplmGuiContext::replay_frame`].
+    ///
+    /// Start recording before the first frame of a fresh context, replays are only deterministic
+    /// if egui starts from the same state. A running recording is finished first.
+    #[cfg(feature = "recording")]
+    pub fn start_recording(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), crate::Error> {
+        self.stop_recording();
+        self.recorder = Some(InputRecorder::create(path.as_ref())?);
+        Ok(())
+    }
+
+    #[cfg(feature = "recording")]
+    pub fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish();
+        }
+    }
+
+    #[cfg(feature = "recording")]
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Makes the next [`XplmInputState::take_egui_input`] return `input` unchanged.
+    #[cfg(feature = "recording")]
+    pub(crate) fn set_replayed_input(&mut self, input: egui::RawInput) {
+        self.replayed_input = Some(input);
+    }
+
+    #[cfg(feature = "recording")]
+    fn record_event(&mut self, event: impl FnOnce() -> RawEvent) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_event(event());
+        }
+    }
+
+    #[cfg(feature = "recording")]
+    fn record_frame(&mut self, input: &egui::RawInput) {
+        let failed = self
+            .recorder
+            .as_mut()
+            .is_some_and(|recorder| !recorder.record_frame(input));
+        if failed {
+            self.stop_recording();
+        }
+    }
+
+    /// Whether anything happened since the last frame that egui needs to know about.
+    pub(crate) fn has_pending_input(&self) -> bool {
+        !self.egui_input.events.is_empty()
+            || self.egui_input.screen_rect != self.last_screen_rect
+    }
+
+    pub(crate) fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+        self.last_frame_time = None;
+    }
+
+    /// Sets the scale used to convert the window's boxels into egui points.
+    ///
+    /// X-Plane's window coordinates are in boxels, which already include the user interface scale:
+    /// a boxel covers `ui_scale` framebuffer pixels. Unless `pixels_per_point` differs from `ui_scale`,
+    /// one point is one boxel.
+    pub(crate) fn set_scale(&mut self, ui_scale: f32, pixels_per_point: f32) {
+        self.current_pixels_per_point = pixels_per_point;
+        self.points_per_boxel = ui_scale / pixels_per_point;
+    }
+
+    /// Remembers what egui did with the input of the frame that just ran,
+    /// so that the next XPLM events can be consumed or passed on accordingly.
+    pub(crate) fn end_frame(&mut self, ctx: &egui::CtxRef) {
+        self.pointer_over_area = ctx.is_pointer_over_area();
+        self.wants_pointer_input = ctx.wants_pointer_input();
+    }
+
+    /// In click-through mode, clicks that don't land on an egui area are passed on to X-Plane.
+    ///
+    /// This is useful for transparent windows that only show a few floating egui windows.
+    /// Otherwise, every click inside the window is consumed. Defaults to `false`.
+    pub fn set_click_through(&mut self, enabled: bool) {
+        self.click_through = enabled;
+    }
+
+    /// How many points one click of the scroll wheel scrolls. Defaults to `50.0`.
+    pub fn set_scroll_points_per_line(&mut self, points: f32) {
+        self.scroll_points_per_line = points;
+    }
+
+    /// Whether vertical scrolling with shift held scrolls horizontally instead. Defaults to `true`.
+    pub fn set_shift_scrolls_horizontally(&mut self, enabled: bool) {
+        self.shift_scrolls_horizontally = enabled;
+    }
+
+    pub(crate) fn set_clipboard(&mut self, clipboard: Box<dyn Clipboard>) {
+        self.clipboard = clipboard;
+    }
+
+    /// Writes text that was copied or cut in egui to the clipboard.
+    pub(crate) fn handle_copied_text(&mut self, copied_text: String) {
+        if !copied_text.is_empty() {
+            self.clipboard.set(copied_text);
+        }
+    }
+
+    /// Sets the cursor to show from now on. `custom` cursors are drawn into the window, so X-Plane hides its own.
+    pub(crate) fn set_cursor_icon(&mut self, cursor_icon: egui::CursorIcon, custom: bool) {
+        self.current_cursor_icon = cursor_icon;
+        self.custom_cursor = custom;
     }
 
     pub fn keyboard_event(
         &mut self,
-        _window: &xplm::window::Window,
+        _window: &(impl WindowBackend + ?Sized),
         event: xplm::window::KeyEvent,
     ) {
-        // TODO char input
-        self.egui_input.modifiers = Modifiers {
-            alt: event.option_pressed(),
-            ctrl: event.control_pressed(),
-            shift: event.shift_pressed(),
-            mac_cmd: false,
-            command: event.control_pressed(),
-        };
+        let modifiers = xplm_modifiers(
+            event.control_pressed(),
+            event.option_pressed(),
+            event.shift_pressed(),
+        );
         let pressed = match event.action() {
             xplm::window::KeyAction::Press => true,
             xplm::window::KeyAction::Release => false,
         };
-        if pressed {
-            if let Some(c) = event.char() {
+        self.key_input(event.key(), event.char(), modifiers, pressed);
+    }
+
+    /// Like [`XplmInputState::keyboard_event`], but with the parts of the event passed separately.
+    pub fn key_input(
+        &mut self,
+        key: Key,
+        char: Option<char>,
+        modifiers: Modifiers,
+        pressed: bool,
+    ) {
+        #[cfg(feature = "recording")]
+        self.record_event(|| RawEvent::Key {
+            key: format!("{key:?}"),
+            char,
+            modifiers,
+            pressed,
+        });
+        self.egui_input.modifiers = modifiers;
+        if pressed && !modifiers.ctrl {
+            if let Some(c) = char.filter(|c| !c.is_control()) {
                 self.egui_input
                     .events
                     .push(egui::Event::Text(c.to_string()))
             }
         }
 
-        if let Some(key) = xplm_to_egui_key(&event) {
+        if let Some(key) = xplm_to_egui_key(key) {
+            if pressed {
+                let is_cut = (modifiers.command && key == egui::Key::X)
+                    || (modifiers.shift && key == egui::Key::Delete);
+                let is_copy =
+                    modifiers.command && (key == egui::Key::C || key == egui::Key::Insert);
+                let is_paste = (modifiers.command && key == egui::Key::V)
+                    || (modifiers.shift && key == egui::Key::Insert);
+                if is_cut {
+                    self.egui_input.events.push(egui::Event::Cut);
+                } else if is_copy {
+                    self.egui_input.events.push(egui::Event::Copy);
+                } else if is_paste {
+                    if let Some(contents) = self.clipboard.get() {
+                        self.egui_input.events.push(egui::Event::Text(contents));
+                    }
+                }
+            }
             self.egui_input.events.push(egui::Event::Key {
                 key,
                 pressed,
@@ -52,72 +280,201 @@ impl XplmInputState {
         }
     }
 
+    /// Handles a (left) mouse click, which is reported to egui as [`egui::PointerButton::Primary`].
+    ///
+    /// Returns whether the click is consumed. If not, X-Plane passes it on to whatever lies below the window.
     pub fn mouse_event(
         &mut self,
-        window: &xplm::window::Window,
+        window: &(impl WindowBackend + ?Sized),
         event: xplm::window::MouseEvent,
     ) -> bool {
-        let geo = window.geometry();
-        let pos = Pos2::new(
-            (event.position().x() - geo.left()) as f32,
-            (geo.top() - event.position().y()) as f32,
-        );
+        self.mouse_button_event(window, event, egui::PointerButton::Primary)
+    }
+
+    /// Handles a right click, which is reported to egui as [`egui::PointerButton::Secondary`],
+    /// so that e.g. [`egui::Response::context_menu`] works.
+    ///
+    /// Forward the events of the window's right click handler (`handleRightClickFunc` of
+    /// `XPLMCreateWindowEx`) here, as [`crate::XplmWindow`] does. The return value has the same meaning as for
+    /// [`XplmInputState::mouse_event`].
+    pub fn right_click_event(
+        &mut self,
+        window: &(impl WindowBackend + ?Sized),
+        event: xplm::window::MouseEvent,
+    ) -> bool {
+        self.mouse_button_event(window, event, egui::PointerButton::Secondary)
+    }
+
+    /// Handles a click of an arbitrary mouse button.
+    ///
+    /// X-Plane only reports left and right clicks to windows, this is meant for
+    /// plugins that get other buttons (e.g. the middle one) from elsewhere.
+    pub fn mouse_button_event(
+        &mut self,
+        window: &(impl WindowBackend + ?Sized),
+        event: xplm::window::MouseEvent,
+        button: egui::PointerButton,
+    ) -> bool {
+        self.mouse_input(window, event.position(), event.action(), button)
+    }
+
+    /// Like [`XplmInputState::mouse_button_event`], but with the parts of the event passed separately.
+    pub fn mouse_input(
+        &mut self,
+        window: &(impl WindowBackend + ?Sized),
+        position: Point<i32>,
+        action: MouseAction,
+        button: egui::PointerButton,
+    ) -> bool {
+        #[cfg(feature = "recording")]
+        self.record_event(|| RawEvent::Mouse {
+            x: position.x(),
+            y: position.y(),
+            action: format!("{action:?}"),
+            button,
+        });
+        let pos = self.pos_in_points(window, position);
         //let pos = Pos2::new(event.position().x() as f32, event.position().y() as f32);
 
-        let event = match event.action() {
-            xplm::window::MouseAction::Drag => egui::Event::PointerMoved(pos),
-            xplm::window::MouseAction::Down => egui::Event::PointerButton {
-                pos,
-                button: egui::PointerButton::Primary,
-                pressed: true,
-                modifiers: self.egui_input.modifiers,
-            },
-            xplm::window::MouseAction::Up => egui::Event::PointerButton {
+        let captured = &mut self.pointer_captured[button as usize];
+        let event = match action {
+            MouseAction::Drag => egui::Event::PointerMoved(pos),
+            MouseAction::Down => {
+                *captured = !self.click_through
+                    || self.pointer_over_area
+                    || self.wants_pointer_input;
+                if !*captured {
+                    // X-Plane won't send the matching release, so egui must not see the press either
+                    return false;
+                }
+                egui::Event::PointerButton {
+                    pos,
+                    button,
+                    pressed: true,
+                    modifiers: self.egui_input.modifiers,
+                }
+            }
+            MouseAction::Up => egui::Event::PointerButton {
                 pos,
-                button: egui::PointerButton::Primary,
+                button,
                 pressed: false,
                 modifiers: self.egui_input.modifiers,
             },
         };
+        let captured = *captured;
         self.egui_input.events.push(event);
-        false
+        captured
     }
 
+    /// Returns whether egui uses the scroll, so that X-Plane doesn't zoom the camera as well.
     pub fn scroll_event(
         &mut self,
-        _window: &xplm::window::Window,
-        _event: xplm::window::ScrollEvent,
+        window: &(impl WindowBackend + ?Sized),
+        event: xplm::window::ScrollEvent,
     ) -> bool {
-        false
+        self.scroll_input(window, event.position(), event.scroll_x(), event.scroll_y())
+    }
+
+    /// Like [`XplmInputState::scroll_event`], but with the parts of the event passed separately.
+    pub fn scroll_input(
+        &mut self,
+        window: &(impl WindowBackend + ?Sized),
+        position: Point<i32>,
+        scroll_x: i32,
+        scroll_y: i32,
+    ) -> bool {
+        #[cfg(feature = "recording")]
+        self.record_event(|| RawEvent::Scroll {
+            x: position.x(),
+            y: position.y(),
+            scroll_x,
+            scroll_y,
+        });
+        let pos = self.pos_in_points(window, position);
+        self.egui_input.events.push(egui::Event::PointerMoved(pos));
+
+        let mut delta =
+            egui::vec2(scroll_x as f32, scroll_y as f32) * self.scroll_points_per_line;
+        if self.shift_scrolls_horizontally && self.egui_input.modifiers.shift && delta.x == 0.0 {
+            delta = egui::vec2(delta.y, 0.0);
+        }
+        self.egui_input.events.push(egui::Event::Scroll(delta));
+
+        self.pointer_over_area
     }
 
     pub fn cursor(
         &mut self,
-        window: &xplm::window::Window,
-        position: xplm::geometry::Point<i32>,
+        window: &(impl WindowBackend + ?Sized),
+        position: Point<i32>,
     ) -> xplm::window::Cursor {
         let geo = window.geometry();
         self.egui_input.screen_rect = Some(Rect {
             min: Pos2 { x: 0.0, y: 0.0 },
             max: Pos2 {
-                x: (geo.right() - geo.left()) as f32,
-                y: (geo.top() - geo.bottom()) as f32,
+                x: (geo.right() - geo.left()) as f32 * self.points_per_boxel,
+                y: (geo.top() - geo.bottom()) as f32 * self.points_per_boxel,
             },
         });
 
         //let pos = Pos2::new(position.x() as f32, position.y() as f32);
-        let pos = Pos2::new(
-            (position.x() - geo.left()) as f32,
-            (geo.top() - position.y()) as f32,
-        );
-        self.egui_input.events.push(egui::Event::PointerMoved(pos));
-        xplm::window::Cursor::Default
+        let pos = self.pos_in_points(window, position);
+        // X-Plane asks for the cursor every frame, only actual movement is news for egui
+        if self.pointer_pos_in_points != Some(pos) {
+            #[cfg(feature = "recording")]
+            self.record_event(|| RawEvent::Cursor {
+                x: position.x(),
+                y: position.y(),
+            });
+            self.pointer_pos_in_points = Some(pos);
+            self.egui_input.events.push(egui::Event::PointerMoved(pos));
+        }
+        if self.custom_cursor {
+            return xplm::window::Cursor::None;
+        }
+        egui_to_xplm_cursor(self.current_cursor_icon)
+    }
+
+    /// Converts global X-Plane boxel coordinates into egui points relative to the window's top left corner.
+    fn pos_in_points(&self, window: &(impl WindowBackend + ?Sized), position: Point<i32>) -> Pos2 {
+        let geo = window.geometry();
+        Pos2::new(
+            (position.x() - geo.left()) as f32 * self.points_per_boxel,
+            (geo.top() - position.y()) as f32 * self.points_per_boxel,
+        )
+    }
+}
+
+/// X-Plane can only show its default cursor, so everything except hiding it needs a custom cursor.
+fn egui_to_xplm_cursor(cursor_icon: egui::CursorIcon) -> xplm::window::Cursor {
+    match cursor_icon {
+        egui::CursorIcon::None => xplm::window::Cursor::None,
+        egui::CursorIcon::Default => xplm::window::Cursor::Default,
+        _ => xplm::window::Cursor::Arrow,
     }
 }
-fn xplm_to_egui_key(xplm_event: &xplm::window::KeyEvent) -> Option<egui::Key> {
+
+/// The egui modifiers for the modifier keys X-Plane reports. Control doubles as the command key.
+pub(crate) fn xplm_modifiers(control: bool, option: bool, shift: bool) -> Modifiers {
+    Modifiers {
+        alt: option,
+        ctrl: control,
+        shift,
+        mac_cmd: false,
+        command: control,
+    }
+}
+
+/// Maps X-Plane's virtual keys to egui keys.
+///
+/// The match is deliberately exhaustive: every X-Plane key is either mapped or explicitly ignored,
+/// so new keys can't silently get lost. Numpad operators and punctuation have no egui key, but still reach
+/// text edits as [`egui::Event::Text`] through [`xplm::window::KeyEvent::char`]. Function keys and the other
+/// keys without an egui counterpart don't reach egui at all.
+fn xplm_to_egui_key(key: Key) -> Option<egui::Key> {
     use egui::Key as e;
     use xplm::window::Key as x;
-    let key = match xplm_event.key() {
+    let key = match key {
         x::Left => e::ArrowLeft,
         x::Up => e::ArrowUp,
         x::Right => e::ArrowRight,
@@ -127,11 +484,14 @@ fn xplm_to_egui_key(xplm_event: &xplm::window::KeyEvent) -> Option<egui::Key> {
         x::Tab => e::Tab,
         x::Return => e::Enter,
         x::Enter => e::Enter,
+        x::NumpadEnter => e::Enter,
         x::Escape => e::Escape,
         x::Space => e::Space,
 
         x::End => e::End,
         x::Home => e::Home,
+        x::Prior => e::PageUp,
+        x::Next => e::PageDown,
         x::Insert => e::Insert,
         x::Delete => e::Delete,
 
@@ -172,9 +532,237 @@ fn xplm_to_egui_key(xplm_event: &xplm::window::KeyEvent) -> Option<egui::Key> {
         x::X => e::X,
         x::Y => e::Y,
         x::Z => e::Z,
-        _ => {
-            return None;
-        }
+
+        // Produce text, but have no egui key
+        x::Multiply | x::Add | x::Separator | x::Subtract | x::Decimal | x::Divide => return None,
+        x::NumpadEqual | x::Equal | x::Minus => return None,
+        x::OpeningBrace | x::ClosingBrace | x::Quote | x::Semicolon | x::Backslash => return None,
+        x::Comma | x::Slash | x::Period | x::Backquote => return None,
+
+        // No egui counterpart
+        x::Clear | x::Select | x::Print | x::Execute | x::Snapshot | x::Help => return None,
+        x::F1 | x::F2 | x::F3 | x::F4 | x::F5 | x::F6 | x::F7 | x::F8 => return None,
+        x::F9 | x::F10 | x::F11 | x::F12 | x::F13 | x::F14 | x::F15 | x::F16 => return None,
+        x::F17 | x::F18 | x::F19 | x::F20 | x::F21 | x::F22 | x::F23 | x::F24 => return None,
     };
     Some(key)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_xplm_key_is_mapped_or_ignored() {
+        use egui::Key as e;
+        use xplm::window::Key as x;
+        #[rustfmt::skip]
+        let table = [
+            (x::Left, Some(e::ArrowLeft)), (x::Up, Some(e::ArrowUp)),
+            (x::Right, Some(e::ArrowRight)), (x::Down, Some(e::ArrowDown)),
+            (x::Back, Some(e::Backspace)), (x::Tab, Some(e::Tab)),
+            (x::Return, Some(e::Enter)), (x::Enter, Some(e::Enter)),
+            (x::NumpadEnter, Some(e::Enter)), (x::Escape, Some(e::Escape)),
+            (x::Space, Some(e::Space)), (x::End, Some(e::End)), (x::Home, Some(e::Home)),
+            (x::Prior, Some(e::PageUp)), (x::Next, Some(e::PageDown)),
+            (x::Insert, Some(e::Insert)), (x::Delete, Some(e::Delete)),
+            (x::Key0, Some(e::Num0)), (x::Key1, Some(e::Num1)), (x::Key2, Some(e::Num2)),
+            (x::Key3, Some(e::Num3)), (x::Key4, Some(e::Num4)), (x::Key5, Some(e::Num5)),
+            (x::Key6, Some(e::Num6)), (x::Key7, Some(e::Num7)), (x::Key8, Some(e::Num8)),
+            (x::Key9, Some(e::Num9)),
+            (x::Numpad0, Some(e::Num0)), (x::Numpad1, Some(e::Num1)), (x::Numpad2, Some(e::Num2)),
+            (x::Numpad3, Some(e::Num3)), (x::Numpad4, Some(e::Num4)), (x::Numpad5, Some(e::Num5)),
+            (x::Numpad6, Some(e::Num6)), (x::Numpad7, Some(e::Num7)), (x::Numpad8, Some(e::Num8)),
+            (x::Numpad9, Some(e::Num9)),
+            (x::A, Some(e::A)), (x::B, Some(e::B)), (x::C, Some(e::C)), (x::D, Some(e::D)),
+            (x::E, Some(e::E)), (x::F, Some(e::F)), (x::G, Some(e::G)), (x::H, Some(e::H)),
+            (x::I, Some(e::I)), (x::J, Some(e::J)), (x::K, Some(e::K)), (x::L, Some(e::L)),
+            (x::M, Some(e::M)), (x::N, Some(e::N)), (x::O, Some(e::O)), (x::P, Some(e::P)),
+            (x::Q, Some(e::Q)), (x::R, Some(e::R)), (x::S, Some(e::S)), (x::T, Some(e::T)),
+            (x::U, Some(e::U)), (x::V, Some(e::V)), (x::W, Some(e::W)), (x::X, Some(e::X)),
+            (x::Y, Some(e::Y)), (x::Z, Some(e::Z)),
+            (x::Multiply, None), (x::Add, None), (x::Separator, None), (x::Subtract, None),
+            (x::Decimal, None), (x::Divide, None), (x::NumpadEqual, None), (x::Equal, None),
+            (x::Minus, None), (x::OpeningBrace, None), (x::ClosingBrace, None), (x::Quote, None),
+            (x::Semicolon, None), (x::Backslash, None), (x::Comma, None), (x::Slash, None),
+            (x::Period, None), (x::Backquote, None),
+            (x::Clear, None), (x::Select, None), (x::Print, None), (x::Execute, None),
+            (x::Snapshot, None), (x::Help, None),
+            (x::F1, None), (x::F2, None), (x::F3, None), (x::F4, None), (x::F5, None),
+            (x::F6, None), (x::F7, None), (x::F8, None), (x::F9, None), (x::F10, None),
+            (x::F11, None), (x::F12, None), (x::F13, None), (x::F14, None), (x::F15, None),
+            (x::F16, None), (x::F17, None), (x::F18, None), (x::F19, None), (x::F20, None),
+            (x::F21, None), (x::F22, None), (x::F23, None), (x::F24, None),
+        ];
+        for (xplm_key, egui_key) in table {
+            assert_eq!(xplm_to_egui_key(xplm_key.clone()), egui_key, "{xplm_key:?}");
+        }
+    }
+
+    /// A clock that returns whatever time the test sets.
+    #[derive(Clone, Default)]
+    struct FakeClock(std::rc::Rc<std::cell::Cell<f64>>);
+
+    impl Clock for FakeClock {
+        fn now(&mut self) -> f64 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn time_and_predicted_dt_come_from_the_clock() {
+        let clock = FakeClock::default();
+        let mut input_state = XplmInputState::default();
+        input_state.set_clock(Box::new(clock.clone()));
+
+        clock.0.set(1.0);
+        let input = input_state.take_egui_input();
+        assert_eq!(input.time, Some(1.0));
+        // Without a previous frame, egui's default is kept
+        assert_eq!(input.predicted_dt, egui::RawInput::default().predicted_dt);
+
+        clock.0.set(1.05);
+        let input = input_state.take_egui_input();
+        assert_eq!(input.time, Some(1.05));
+        assert!((input.predicted_dt - 0.05).abs() < 1e-6, "{}", input.predicted_dt);
+    }
+
+    #[test]
+    fn predicted_dt_is_clamped() {
+        let clock = FakeClock::default();
+        let mut input_state = XplmInputState::default();
+        input_state.set_clock(Box::new(clock.clone()));
+        input_state.take_egui_input();
+
+        // A long pause, e.g. while loading scenery
+        clock.0.set(5.0);
+        assert_eq!(input_state.take_egui_input().predicted_dt, 0.1);
+
+        // A clock that breaks its promise and goes back
+        clock.0.set(4.0);
+        assert_eq!(input_state.take_egui_input().predicted_dt, 0.0);
+    }
+
+    /// A 200 × 200 boxel window with its top left corner at (100, 500).
+    fn mock_window() -> crate::mock::MockWindow {
+        crate::mock::MockWindow::new(xplm::geometry::Rect::from_left_top_right_bottom(
+            100, 500, 300, 300,
+        ))
+    }
+
+    #[test]
+    fn key_presses_are_text_and_key_events() {
+        let mut input_state = XplmInputState::default();
+        let modifiers = Modifiers::default();
+        input_state.key_input(Key::A, Some('a'), modifiers, true);
+        input_state.key_input(Key::A, Some('a'), modifiers, false);
+        // Control characters are no text
+        input_state.key_input(Key::Return, Some('\r'), modifiers, true);
+        // Neither are keys without an egui counterpart
+        input_state.key_input(Key::F1, None, modifiers, true);
+        assert_eq!(
+            input_state.take_egui_input().events,
+            [
+                egui::Event::Text("a".into()),
+                egui::Event::Key { key: egui::Key::A, pressed: true, modifiers },
+                egui::Event::Key { key: egui::Key::A, pressed: false, modifiers },
+                egui::Event::Key { key: egui::Key::Enter, pressed: true, modifiers },
+            ]
+        );
+    }
+
+    #[test]
+    fn clipboard_shortcuts() {
+        let mut input_state = XplmInputState::default();
+        let mut clipboard = crate::clipboard::MemoryClipboard::default();
+        clipboard.set("pasted".into());
+        input_state.set_clipboard(Box::new(clipboard));
+        let modifiers = Modifiers {
+            ctrl: true,
+            command: true,
+            ..Default::default()
+        };
+        input_state.key_input(Key::C, Some('c'), modifiers, true);
+        input_state.key_input(Key::V, Some('v'), modifiers, true);
+        assert_eq!(
+            input_state.take_egui_input().events,
+            [
+                egui::Event::Copy,
+                egui::Event::Key { key: egui::Key::C, pressed: true, modifiers },
+                egui::Event::Text("pasted".into()),
+                egui::Event::Key { key: egui::Key::V, pressed: true, modifiers },
+            ]
+        );
+    }
+
+    #[test]
+    fn scrolling_moves_the_pointer_and_scrolls_by_lines() {
+        let window = mock_window();
+        let mut input_state = XplmInputState::default();
+        let position = Point::from((110, 490));
+        let pos = Pos2::new(10.0, 10.0);
+
+        // Not over an egui area, so X-Plane gets the scroll as well
+        assert!(!input_state.scroll_input(&window, position, 0, 1));
+        input_state.egui_input.modifiers.shift = true;
+        input_state.scroll_input(&window, position, 0, -2);
+        assert_eq!(
+            input_state.take_egui_input().events,
+            [
+                egui::Event::PointerMoved(pos),
+                egui::Event::Scroll(egui::vec2(0.0, 50.0)),
+                egui::Event::PointerMoved(pos),
+                egui::Event::Scroll(egui::vec2(-100.0, 0.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn cursor_sets_the_screen_rect_and_reports_movement() {
+        let window = mock_window();
+        let mut input_state = XplmInputState::default();
+        // Points twice as large as boxels
+        input_state.set_scale(1.0, 0.5);
+
+        let cursor = input_state.cursor(&window, Point::from((130, 480)));
+        assert!(matches!(cursor, xplm::window::Cursor::Default), "{cursor:?}");
+        // X-Plane asks again without the mouse having moved
+        input_state.cursor(&window, Point::from((130, 480)));
+        input_state.set_cursor_icon(egui::CursorIcon::Text, true);
+        let cursor = input_state.cursor(&window, Point::from((140, 480)));
+        assert!(matches!(cursor, xplm::window::Cursor::None), "{cursor:?}");
+
+        let input = input_state.take_egui_input();
+        assert_eq!(
+            input.screen_rect,
+            Some(Rect::from_min_max(Pos2::ZERO, Pos2::new(400.0, 400.0)))
+        );
+        assert_eq!(
+            input.events,
+            [
+                egui::Event::PointerMoved(Pos2::new(60.0, 40.0)),
+                egui::Event::PointerMoved(Pos2::new(80.0, 40.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn right_clicks_are_secondary_button_events() {
+        let window = mock_window();
+        let mut input_state = XplmInputState::default();
+        let position = Point::from((130, 480));
+        let button = egui::PointerButton::Secondary;
+
+        assert!(input_state.mouse_input(&window, position, MouseAction::Down, button));
+        assert!(input_state.mouse_input(&window, position, MouseAction::Up, button));
+        let pos = Pos2::new(30.0, 20.0);
+        let modifiers = Modifiers::default();
+        assert_eq!(
+            input_state.take_egui_input().events,
+            [
+                egui::Event::PointerButton { pos, button, pressed: true, modifiers },
+                egui::Event::PointerButton { pos, button, pressed: false, modifiers },
+            ]
+        );
+    }
+}
@@ -1,12 +1,55 @@
+use std::time::{Duration, Instant};
+
 use egui::{ClippedMesh, output::OutputEvent};
+use gl_debug::GlDebugOutput;
+pub use backend::{SimBackend, WindowBackend, XplmBackend};
+pub use clipboard::{Clipboard, MemoryClipboard};
+#[cfg(feature = "clipboard")]
+pub use clipboard::SystemClipboard;
+pub use clock::{Clock, RealTimeClock, SimTimeClock};
+#[cfg(feature = "epi")]
+pub use epi_backend::EpiWindow;
+pub use error::{Error, ShaderStage};
 use input::XplmInputState;
-pub use misc_util::check_gl_error;
+use misc_util::check_texture_data;
+pub use misc_util::{
+    check_gl_error, gl_error, gl_error_policy, set_gl_error_policy, take_gl_errors,
+    GlErrorPolicy,
+};
 use painter::Painter;
-use xplm::data::{borrowed::DataRef, ArrayRead};
+pub use profiler::{FrameProfile, Phase, Profiler};
+pub use painter::{ColorPipeline, TextureOwnership};
+pub use shader_version::ShaderVersion;
+pub use window::{App, EguiWindow};
+pub use xplm_window::{GuiDelegate, XplmWindow, XplmWindowRef};
+
+/// Logs a line through the [`SimBackend`] of the last created context.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::misc_util::log(&format!($($arg)*))
+    };
+}
 
+mod backend;
+mod clipboard;
+mod clock;
+#[cfg(feature = "epi")]
+mod epi_backend;
+mod error;
+mod gl_debug;
 mod input;
 mod misc_util;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+#[cfg(feature = "offscreen")]
+pub mod offscreen;
 mod painter;
+mod profiler;
+#[cfg(feature = "recording")]
+pub mod recording;
+mod shader_version;
+mod window;
+mod xplm_window;
 
 pub fn init_gl_context() -> glow::Context {
         gl_loader::init_gl();
@@ -19,66 +62,561 @@ pub struct XplmGuiContext {
     painter: Painter,
     pub input_state: XplmInputState, //TODO proper abstraction
     clipped_meshes: Vec<ClippedMesh>,
-    viewport: DataRef<[i32]>,
+    backend: Box<dyn SimBackend>,
+    pixels_per_point_override: Option<f32>,
+    pixels_per_point: f32,
+    /// X-Plane's user interface scale, i.e. how many framebuffer pixels a boxel covers.
+    ui_scale: f32,
+    max_size_points: Option<egui::Vec2>,
+    custom_cursors: Vec<(egui::CursorIcon, CustomCursor)>,
+    cursor_icon: egui::CursorIcon,
+    repaint_on_demand: bool,
+    repaint_requested: bool,
+    repaint_at: Option<Instant>,
+    frame_counters: FrameCounters,
+    profiler: Option<Profiler>,
+    gl_debug_output: Option<GlDebugOutput>,
     has_keyboard_focus: bool,
+    /// Whether the warning about drawing into an empty window was logged already.
+    skipping_empty_window: bool,
+    shut_down: bool,
+}
+
+/// How often [`XplmGuiContext::update`] actually ran egui.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameCounters {
+    /// Frames in which the ui was run and tessellated.
+    pub executed: u64,
+    /// Frames in which the meshes of an earlier frame were drawn again.
+    pub skipped: u64,
+}
+
+/// How [`XplmGuiContext`] renders with OpenGL.
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// The GLSL dialect of the shaders, detected from the OpenGL context if `None`.
+    pub shader_version: Option<ShaderVersion>,
+    /// Put in front of the shaders, e.g. additional `#define`s.
+    pub shader_prefix: String,
+    pub color_pipeline: ColorPipeline,
+}
+
+/// A cursor image drawn by egui instead of X-Plane's cursor.
+struct CustomCursor {
+    texture: egui::TextureId,
+    size: egui::Vec2,
+    hotspot: egui::Vec2,
 }
 
 impl XplmGuiContext {
-    pub fn new() -> Result<Self, String> {
-        let gl = init_gl_context();
-        let painter = Painter::new(&gl)?;
+    pub fn new() -> Result<Self, Error> {
+        Self::with_options(RenderOptions::default())
+    }
+
+    /// Like [`XplmGuiContext::new`], but with control over how the ui is rendered.
+    pub fn with_options(options: RenderOptions) -> Result<Self, Error> {
+        Self::with_backend(init_gl_context(), XplmBackend::default(), options)
+    }
+
+    /// Creates a context that talks to `backend` instead of X-Plane, e.g. the `mock::MockBackend` of the `mock` feature,
+    /// and renders with `gl`, which must be current.
+    pub fn with_backend<B: SimBackend + 'static>(
+        gl: glow::Context,
+        backend: B,
+        options: RenderOptions,
+    ) -> Result<Self, Error> {
+        misc_util::set_logger(B::log);
+        let painter = Painter::new(
+            &gl,
+            options.shader_version,
+            &options.shader_prefix,
+            options.color_pipeline,
+        )?;
         Ok(Self {
             gl,
             painter,
             ctx: Default::default(),
             input_state: Default::default(),
             clipped_meshes: vec![],
-            viewport: DataRef::find("sim/graphics/view/viewport").unwrap(),
+            backend: Box::new(backend),
+            pixels_per_point_override: None,
+            pixels_per_point: 1.0,
+            ui_scale: 1.0,
+            max_size_points: None,
+            custom_cursors: vec![],
+            cursor_icon: egui::CursorIcon::Default,
+            repaint_on_demand: false,
+            repaint_requested: true,
+            repaint_at: None,
+            frame_counters: Default::default(),
+            profiler: None,
+            gl_debug_output: None,
             has_keyboard_focus: false,
+            skipping_empty_window: false,
+            shut_down: false,
         })
     }
-    pub fn update(&mut self, window: &xplm::window::Window, run_ui: impl FnOnce(&egui::CtxRef)) {
+
+    /// Deletes all OpenGL resources (shaders, buffers and textures) of this context.
+    ///
+    /// Must be called while X-Plane's OpenGL context is current, which is the case in all XPLM callbacks,
+    /// including `XPluginDisable` and `XPluginStop`. Afterwards, [`XplmGuiContext::update`] and
+    /// [`XplmGuiContext::draw`] do nothing, and textures can't be created or updated anymore;
+    /// create a new context e.g. when the plugin is enabled again.
+    /// This is also done when the context is dropped.
+    pub fn shutdown(&mut self) {
+        if self.shut_down {
+            return;
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.destroy(&self.gl);
+        }
+        self.profiler = None;
+        self.custom_cursors.clear();
+        self.clipped_meshes.clear();
+        self.painter.destroy(&self.gl);
+        self.shut_down = true;
+    }
+
+    /// Whether [`XplmGuiContext::shutdown`] was called.
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+    /// Runs the ui and prepares the meshes for [`XplmGuiContext::draw`].
+    ///
+    /// With [repaint on demand](XplmGuiContext::set_repaint_on_demand), `run_ui` is only called
+    /// if the ui may have changed since the last frame.
+    pub fn update(
+        &mut self,
+        window: &(impl WindowBackend + ?Sized),
+        run_ui: impl FnOnce(&egui::CtxRef),
+    ) {
+        if self.shut_down {
+            return;
+        }
+        let ui_scale = self
+            .backend
+            .ui_scale()
+            .filter(|scale| *scale > 0.0)
+            .unwrap_or(1.0);
+        // By default, a point is a boxel
+        let pixels_per_point = self.pixels_per_point_override.unwrap_or(ui_scale);
+        if self.repaint_on_demand
+            && !self.needs_repaint()
+            && pixels_per_point == self.pixels_per_point
+            && ui_scale == self.ui_scale
+        {
+            self.frame_counters.skipped += 1;
+            return;
+        }
+        self.frame_counters.executed += 1;
+        self.repaint_requested = false;
+        self.repaint_at = None;
+
+        self.pixels_per_point = pixels_per_point;
+        self.ui_scale = ui_scale;
+        self.input_state.set_scale(ui_scale, pixels_per_point);
+        let start = Instant::now();
         let input = self.gather_input();
-        let (output, shapes) = self.ctx.run(input, run_ui);
-        if !self.has_keyboard_focus && self.ctx.wants_keyboard_input() {
-            window.take_keyboard_focus()
-        } 
-        if self.has_keyboard_focus && !self.ctx.wants_keyboard_input() {
-            window.loose_keyboard_focus()
-        } 
-        handle_output(window, output);
+        self.record(Phase::Input, start);
+
+        let start = Instant::now();
+        let profiler = &self.profiler;
+        let (output, shapes) = self.ctx.run(input, |ctx| {
+            run_ui(ctx);
+            if let Some(profiler) = profiler {
+                profiler.show_overlay(ctx);
+            }
+        });
+        self.record(Phase::Run, start);
+
+        self.repaint_requested = output.needs_repaint;
+        self.input_state.end_frame(&self.ctx);
+        self.cursor_icon = output.cursor_icon;
+        let custom_cursor = self.custom_cursor(self.cursor_icon).is_some();
+        self.input_state
+            .set_cursor_icon(self.cursor_icon, custom_cursor);
+        let focus_gained = handle_output(&mut self.input_state, output);
+        self.sync_keyboard_focus(window, focus_gained || self.ctx.wants_keyboard_input());
+        let start = Instant::now();
         self.clipped_meshes = self.ctx.tessellate(shapes);
+        self.record(Phase::Tessellate, start);
+        if let Some(mesh) = self.custom_cursor_mesh() {
+            self.clipped_meshes
+                .push(ClippedMesh(egui::Rect::EVERYTHING, mesh));
+        }
     }
 
-    pub fn draw(&mut self, window: &xplm::window::Window) {
-        self.painter.upload_egui_texture(&self.gl, &self.ctx.font_image());
+    /// Takes or releases the keyboard focus of `window`, so that X-Plane only sends
+    /// key presses while egui wants them.
+    fn sync_keyboard_focus(&mut self, window: &(impl WindowBackend + ?Sized), wants_focus: bool) {
+        if wants_focus && !self.has_keyboard_focus {
+            window.take_keyboard_focus();
+            self.has_keyboard_focus = true;
+        } else if !wants_focus && self.has_keyboard_focus {
+            window.release_keyboard_focus();
+            self.has_keyboard_focus = false;
+        }
+    }
+
+    /// Only run egui if input arrived, egui or the app asked for a repaint, or a repaint timer expired.
+    /// Otherwise, the meshes of the last frame are drawn again. This saves precious time in
+    /// the sim's frame budget for mostly static uis. Defaults to `false`.
+    pub fn set_repaint_on_demand(&mut self, enabled: bool) {
+        self.repaint_on_demand = enabled;
+    }
+
+    /// Runs the ui during the next [`XplmGuiContext::update`], even with repaint on demand.
+    pub fn request_repaint(&mut self) {
+        self.repaint_requested = true;
+    }
+
+    /// Runs the ui once `delay` has passed, e.g. to refresh values that change outside of egui.
+    pub fn request_repaint_after(&mut self, delay: Duration) {
+        let at = Instant::now() + delay;
+        self.repaint_at = Some(self.repaint_at.map_or(at, |earlier| earlier.min(at)));
+    }
+
+    /// How many frames were run and skipped so far.
+    pub fn frame_counters(&self) -> FrameCounters {
+        self.frame_counters
+    }
+
+    fn needs_repaint(&self) -> bool {
+        self.repaint_requested
+            || self.input_state.has_pending_input()
+            || self.repaint_at.is_some_and(|at| at <= Instant::now())
+    }
+
+    /// The cursor egui asked for during the last frame.
+    pub fn cursor_icon(&self) -> egui::CursorIcon {
+        self.cursor_icon
+    }
+
+    /// Draws the given image instead of X-Plane's cursor whenever egui shows `cursor_icon`.
+    ///
+    /// X-Plane has no cursors for text or resizing, so this is the way to get e.g. an I-beam above text edits.
+    /// `size` is in points and `hotspot` is the pixel of the image that is placed at the pointer position.
+    pub fn set_custom_cursor(
+        &mut self,
+        cursor_icon: egui::CursorIcon,
+        size: [usize; 2],
+        srgba_pixels: &[egui::Color32],
+        hotspot: [usize; 2],
+    ) -> Result<(), Error> {
+        if self.shut_down {
+            return Err(Error::ShutDown);
+        }
+        self.clear_custom_cursor(cursor_icon);
+        let texture = self.alloc_texture(size, srgba_pixels)?;
+        let cursor = CustomCursor {
+            texture,
+            size: egui::vec2(size[0] as f32, size[1] as f32),
+            hotspot: egui::vec2(hotspot[0] as f32, hotspot[1] as f32),
+        };
+        self.custom_cursors.push((cursor_icon, cursor));
+        Ok(())
+    }
+
+    /// Lets X-Plane draw the cursor for `cursor_icon` again.
+    pub fn clear_custom_cursor(&mut self, cursor_icon: egui::CursorIcon) {
+        if let Some(index) = self
+            .custom_cursors
+            .iter()
+            .position(|(icon, _)| *icon == cursor_icon)
+        {
+            let (_, cursor) = self.custom_cursors.remove(index);
+            self.free_texture(cursor.texture);
+        }
+    }
+
+    fn custom_cursor(&self, cursor_icon: egui::CursorIcon) -> Option<&CustomCursor> {
+        self.custom_cursors
+            .iter()
+            .find(|(icon, _)| *icon == cursor_icon)
+            .map(|(_, cursor)| cursor)
+    }
+
+    fn custom_cursor_mesh(&self) -> Option<egui::epaint::Mesh> {
+        let cursor = self.custom_cursor(self.cursor_icon)?;
+        let pointer_pos = self.ctx.input().pointer.hover_pos()?;
+        let min = pointer_pos - cursor.hotspot;
+        let mut mesh = egui::epaint::Mesh::with_texture(cursor.texture);
+        mesh.add_rect_with_uv(
+            egui::Rect::from_min_size(min, cursor.size),
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+        Some(mesh)
+    }
+
+    pub fn draw(&mut self, window: &(impl WindowBackend + ?Sized)) {
+        if self.shut_down {
+            return;
+        }
+        let start = Instant::now();
+        let uploaded = self
+            .painter
+            .upload_egui_texture(&self.gl, &self.ctx.font_image());
+        self.record(Phase::TextureUpload, start);
+        if let Err(err) = uploaded {
+            log!("egui: skipping frame, {err}");
+            return;
+        }
         let w_geo = window.geometry();
-        let mut viter = self.viewport.as_vec().into_iter();
-        let viewport = [
-            viter.next().unwrap(),
-            viter.next().unwrap(),
-            viter.next().unwrap(),
-            viter.next().unwrap(),
-        ];
+        if w_geo.right() <= w_geo.left() || w_geo.top() <= w_geo.bottom() {
+            if !self.skipping_empty_window {
+                log!("egui: not drawing into empty window {w_geo:?}");
+                self.skipping_empty_window = true;
+            }
+            return;
+        }
+        self.skipping_empty_window = false;
+        let start = Instant::now();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.begin_gpu_paint(&self.gl);
+        }
+        let previous_debug_state = self
+            .gl_debug_output
+            .as_ref()
+            .map(|debug_output| unsafe { debug_output.install(&self.gl) });
         self.painter
-            .paint_meshes(&self.gl, w_geo, viewport, 1.0, &self.clipped_meshes);
+            .paint_meshes(
+                &self.gl,
+                self.backend.as_ref(),
+                w_geo,
+                self.ui_scale,
+                self.pixels_per_point,
+                &self.clipped_meshes,
+            );
+        if let (Some(debug_output), Some(previous)) =
+            (&self.gl_debug_output, previous_debug_state)
+        {
+            unsafe { debug_output.uninstall(&self.gl, previous) };
+        }
+        self.record(Phase::Paint, start);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_gpu_paint(&self.gl);
+            profiler.end_frame(&self.gl);
+        }
     }
 
-}
+    /// Reports errors through the OpenGL debug output (`KHR_debug` or `ARB_debug_output`), which names
+    /// the failing call, instead of asking `glGetError` after every call.
+    ///
+    /// Messages are handled according to the [`GlErrorPolicy`]. While painting, [`check_gl_error`] leaves
+    /// the reporting to the debug output, which avoids the overhead of polling. The debug callback is
+    /// shared by everything in the OpenGL context, so ours is only installed while egui paints; the
+    /// callback and debug output settings of X-Plane or other plugins are restored afterwards.
+    ///
+    /// Returns `false` if the debug output is not supported.
+    pub fn enable_gl_debug_output(&mut self) -> bool {
+        if self.gl_debug_output.is_none() {
+            self.gl_debug_output = GlDebugOutput::load(&self.gl);
+        }
+        self.gl_debug_output.is_some()
+    }
 
-fn handle_output(window: &xplm::window::Window, output: egui::Output) {
-    for event in output.events {
-        match event {
-            OutputEvent::FocusGained(_) => {
-                window.take_keyboard_focus()
+    /// Starts or stops measuring where the time of each frame goes. See [`Profiler`].
+    pub fn set_profiling(&mut self, enabled: bool) {
+        match (enabled, &mut self.profiler) {
+            (true, None) if !self.shut_down => self.profiler = Some(Profiler::new(&self.gl)),
+            (false, Some(profiler)) => {
+                profiler.destroy(&self.gl);
+                self.profiler = None;
             }
-            _ => ()
+            _ => (),
+        }
+    }
+
+    /// The profiler, if [profiling](XplmGuiContext::set_profiling) is enabled.
+    pub fn profiler(&mut self) -> Option<&mut Profiler> {
+        self.profiler.as_mut()
+    }
+
+    fn record(&mut self, phase: Phase, start: Instant) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(phase, start);
+        }
+    }
+
+    /// Overrides the number of framebuffer pixels per egui point.
+    ///
+    /// By default (`None`), X-Plane's user interface scale setting is used, which makes a point as large
+    /// as a boxel, X-Plane's unit for window coordinates.
+    pub fn set_pixels_per_point(&mut self, pixels_per_point: Option<f32>) {
+        self.pixels_per_point_override = pixels_per_point;
+    }
+
+    /// The number of framebuffer pixels per egui point used for the last frame.
+    pub fn pixels_per_point(&self) -> f32 {
+        self.pixels_per_point
+    }
+
+    /// X-Plane's user interface scale during the last frame, i.e. the number of framebuffer pixels per boxel.
+    pub fn ui_scale(&self) -> f32 {
+        self.ui_scale
+    }
+
+    /// Limits the area egui lays out its ui in to the top left `max_size` points of the window.
+    pub fn set_max_size_points(&mut self, max_size: Option<egui::Vec2>) {
+        self.max_size_points = max_size;
+    }
+
+    /// The egui context, e.g. to change the style or fonts outside of a frame.
+    pub fn ctx(&self) -> &egui::CtxRef {
+        &self.ctx
+    }
+
+    /// Replaces the time source of egui, e.g. with a [`SimTimeClock`] to pause animations together with the sim.
+    ///
+    /// Defaults to a [`RealTimeClock`].
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.input_state.set_clock(Box::new(clock));
+    }
+
+    /// Replaces the clipboard used for copy, cut and paste.
+    ///
+    /// Defaults to the system clipboard (with the `clipboard` feature) or a [`MemoryClipboard`].
+    pub fn set_clipboard(&mut self, clipboard: impl Clipboard + 'static) {
+        self.input_state.set_clipboard(Box::new(clipboard));
+    }
+
+    /// Uploads an image to the GPU so it can be shown with e.g. [`egui::Ui::image`].
+    ///
+    /// `srgba_pixels` are premultiplied, row by row from the top left, `size` is (width, height).
+    /// Like all texture functions, this must be called while X-Plane's OpenGL context is current,
+    /// e.g. from a draw or flight loop callback.
+    pub fn alloc_texture(
+        &mut self,
+        size: [usize; 2],
+        srgba_pixels: &[egui::Color32],
+    ) -> Result<egui::TextureId, Error> {
+        if self.shut_down {
+            return Err(Error::ShutDown);
         }
+        self.painter.alloc_user_texture(&self.gl, size, srgba_pixels)
+    }
+
+    /// Like [`XplmGuiContext::alloc_texture`], but takes plain RGBA bytes without premultiplied alpha.
+    pub fn alloc_texture_rgba(
+        &mut self,
+        size: [usize; 2],
+        rgba: &[u8],
+    ) -> Result<egui::TextureId, Error> {
+        check_texture_data(size, rgba.len())?;
+        let pixels = srgba_from_rgba_unmultiplied(rgba);
+        self.alloc_texture(size, &pixels)
+    }
+
+    /// Overwrites a region of a texture created by [`XplmGuiContext::alloc_texture`].
+    ///
+    /// `pos` is the top left corner of the region, which must lie completely within the texture,
+    /// otherwise [`Error::TextureRegion`] is returned.
+    pub fn update_texture(
+        &mut self,
+        tex_id: egui::TextureId,
+        pos: [usize; 2],
+        size: [usize; 2],
+        srgba_pixels: &[egui::Color32],
+    ) -> Result<(), Error> {
+        if self.shut_down {
+            return Err(Error::ShutDown);
+        }
+        self.painter
+            .update_user_texture(&self.gl, tex_id, pos, size, srgba_pixels)
+    }
+
+    /// Like [`XplmGuiContext::update_texture`], but takes plain RGBA bytes without premultiplied alpha.
+    pub fn update_texture_rgba(
+        &mut self,
+        tex_id: egui::TextureId,
+        pos: [usize; 2],
+        size: [usize; 2],
+        rgba: &[u8],
+    ) -> Result<(), Error> {
+        check_texture_data(size, rgba.len())?;
+        let pixels = srgba_from_rgba_unmultiplied(rgba);
+        self.update_texture(tex_id, pos, size, &pixels)
+    }
+
+    /// Shows an existing OpenGL texture, e.g. from [`xplm::draw::generate_texture_number`] or
+    /// another plugin's framebuffer, in egui without copying it.
+    ///
+    /// `gl_name` is the raw OpenGL texture name; `None` is returned for the invalid name `0` and after
+    /// [`XplmGuiContext::shutdown`].
+    /// [`TextureOwnership::Owned`] textures are deleted by [`XplmGuiContext::free_texture`].
+    pub fn register_native_texture(
+        &mut self,
+        gl_name: u32,
+        ownership: TextureOwnership,
+    ) -> Option<egui::TextureId> {
+        if self.shut_down {
+            return None;
+        }
+        let native = glow::NativeTexture(std::num::NonZeroU32::new(gl_name)?);
+        Some(self.painter.register_native_texture(native, ownership))
+    }
+
+    /// Frees a texture created by [`XplmGuiContext::alloc_texture`] or
+    /// [`XplmGuiContext::register_native_texture`].
+    pub fn free_texture(&mut self, tex_id: egui::TextureId) {
+        if let egui::TextureId::User(id) = tex_id {
+            self.painter.free_texture(id);
+        }
+    }
+
+}
+
+impl Drop for XplmGuiContext {
+    fn drop(&mut self) {
+        self.shutdown();
     }
 }
 
+fn srgba_from_rgba_unmultiplied(rgba: &[u8]) -> Vec<egui::Color32> {
+    rgba.chunks_exact(4)
+        .map(|p| egui::Color32::from_rgba_unmultiplied(p[0], p[1], p[2], p[3]))
+        .collect()
+}
+
+/// Returns whether a widget gained the focus.
+fn handle_output(input_state: &mut XplmInputState, output: egui::Output) -> bool {
+    input_state.handle_copied_text(output.copied_text);
+    output
+        .events
+        .iter()
+        .any(|event| matches!(event, OutputEvent::FocusGained(_)))
+}
+
 impl XplmGuiContext {
     fn gather_input(&mut self) -> egui::RawInput {
-        self.input_state.take_egui_input()
+        let mut input = self.input_state.take_egui_input();
+        if let (Some(max_size), Some(rect)) = (self.max_size_points, input.screen_rect.as_mut()) {
+            rect.max = rect.min + rect.size().min(max_size);
+        }
+        input
+    }
+
+    /// Runs the ui like [`XplmGuiContext::update`], but with the next frame of `replay`
+    /// instead of the input X-Plane sent since the last frame.
+    ///
+    /// Returns `false` without running the ui once the recording is over.
+    #[cfg(feature = "recording")]
+    pub fn replay_frame(
+        &mut self,
+        window: &(impl WindowBackend + ?Sized),
+        replay: &mut recording::InputReplay,
+        run_ui: impl FnOnce(&egui::CtxRef),
+    ) -> bool {
+        match replay.next_frame() {
+            Some(frame) => {
+                self.input_state.set_replayed_input(frame.input);
+                self.request_repaint();
+                self.update(window, run_ui);
+                true
+            }
+            None => false,
+        }
     }
 }
@@ -1,79 +1,335 @@
 #![allow(unsafe_code)]
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+    num::NonZeroU32,
+    time::{Duration, Instant},
+};
+
 use glow::{HasContext, NativeTexture};
-use xplm::debugln;
+use xplm::data::{borrowed::DataRef, DataType};
+
+use crate::{Error, ShaderStage};
+
+/// A dataref that is looked up when it is first needed.
+///
+/// If the dataref doesn't exist (yet), e.g. on older sims or before another plugin published it,
+/// the lookup is retried at most every [`LazyDataRef::RETRY_INTERVAL`].
+pub(crate) struct LazyDataRef<T: DataType + ?Sized> {
+    name: &'static str,
+    dataref: Option<DataRef<T>>,
+    last_attempt: Option<Instant>,
+}
+
+impl<T: DataType + ?Sized> LazyDataRef<T> {
+    const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            dataref: None,
+            last_attempt: None,
+        }
+    }
+
+    pub(crate) fn get(&mut self) -> Option<&DataRef<T>> {
+        if self.dataref.is_none() {
+            let retry = self
+                .last_attempt
+                .is_none_or(|last| last.elapsed() >= Self::RETRY_INTERVAL);
+            if retry {
+                self.last_attempt = Some(Instant::now());
+                self.dataref = DataRef::find(self.name).ok();
+            }
+        }
+        self.dataref.as_ref()
+    }
+}
 
-pub(crate) fn srgbtexture2d(gl: &glow::Context, data: &[u8], w: usize, h: usize) -> NativeTexture {
-    assert_eq!(data.len(), w * h * 4);
-    assert!(w >= 1);
-    assert!(h >= 1);
+pub(crate) fn srgbtexture2d(
+    gl: &glow::Context,
+    internal_format: u32,
+    data: &[u8],
+    w: usize,
+    h: usize,
+) -> Result<NativeTexture, Error> {
+    check_texture_data([w, h], data.len())?;
     unsafe {
         //let tex = xplm::draw::generate_texture_number();
-        let tex = gl.create_texture().expect("failed to create a texture");
+        let tex = gl.create_texture().map_err(Error::TextureCreation)?;
         check_gl_error(gl, "after creating texture via xplm");
-        gl.bind_texture(glow::TEXTURE_2D, Some(tex));
-        //xplm::draw::bind_texture(tex, 0);
-        check_gl_error(gl, "after binding texture via xplm");
-
-        gl.tex_parameter_i32(
-            glow::TEXTURE_2D,
-            glow::TEXTURE_MAG_FILTER,
-            glow::LINEAR as i32,
-        );
-        check_gl_error(gl, "after tex parameter");
-        gl.tex_parameter_i32(
-            glow::TEXTURE_2D,
-            glow::TEXTURE_MIN_FILTER,
-            glow::LINEAR as i32,
-        );
-        gl.tex_parameter_i32(
-            glow::TEXTURE_2D,
-            glow::TEXTURE_WRAP_S,
-            glow::CLAMP_TO_EDGE as i32,
-        );
-        gl.tex_parameter_i32(
-            glow::TEXTURE_2D,
-            glow::TEXTURE_WRAP_T,
-            glow::CLAMP_TO_EDGE as i32,
-        );
-        gl.tex_storage_2d(glow::TEXTURE_2D, 1, glow::SRGB8_ALPHA8, w as i32, h as i32);
-        check_gl_error(gl, "after tex storage");
-        gl.tex_sub_image_2d(
-            glow::TEXTURE_2D,
-            0,
-            0,
-            0,
-            w as i32,
-            h as i32,
-            glow::RGBA,
-            glow::UNSIGNED_BYTE,
-            glow::PixelUnpackData::Slice(data),
-        );
-        check_gl_error(gl, "after tex sub image");
-        gl.bind_texture(glow::TEXTURE_2D, None);
-        tex
+        with_bound_texture(gl, tex, || {
+            //xplm::draw::bind_texture(tex, 0);
+            check_gl_error(gl, "after binding texture via xplm");
+
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_MAG_FILTER,
+                glow::LINEAR as i32,
+            );
+            check_gl_error(gl, "after tex parameter");
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_MIN_FILTER,
+                glow::LINEAR as i32,
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_WRAP_S,
+                glow::CLAMP_TO_EDGE as i32,
+            );
+            gl.tex_parameter_i32(
+                glow::TEXTURE_2D,
+                glow::TEXTURE_WRAP_T,
+                glow::CLAMP_TO_EDGE as i32,
+            );
+            gl.tex_storage_2d(glow::TEXTURE_2D, 1, internal_format, w as i32, h as i32);
+            check_gl_error(gl, "after tex storage");
+            gl.tex_sub_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                0,
+                0,
+                w as i32,
+                h as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(data),
+            );
+            check_gl_error(gl, "after tex sub image");
+        });
+        Ok(tex)
+    }
+}
+
+pub(crate) fn update_srgbtexture2d(
+    gl: &glow::Context,
+    tex: NativeTexture,
+    [x, y]: [usize; 2],
+    [w, h]: [usize; 2],
+    data: &[u8],
+) -> Result<(), Error> {
+    check_texture_data([w, h], data.len())?;
+    unsafe {
+        with_bound_texture(gl, tex, || {
+            gl.tex_sub_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                x as i32,
+                y as i32,
+                w as i32,
+                h as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(data),
+            );
+            check_gl_error(gl, "after tex sub image update");
+        });
+    }
+    Ok(())
+}
+
+/// Checks that `len` bytes of RGBA pixels fill a non-empty texture (region) of `size` exactly.
+pub(crate) fn check_texture_data(size: [usize; 2], len: usize) -> Result<(), Error> {
+    let [w, h] = size;
+    if w == 0 || h == 0 {
+        return Err(Error::TextureSize(size));
+    }
+    if w.checked_mul(h).and_then(|pixels| pixels.checked_mul(4)) != Some(len) {
+        return Err(Error::TextureDataLength { size, len });
     }
+    Ok(())
+}
+
+/// Runs `upload` with `tex` bound to `TEXTURE_2D` of texture unit 0.
+///
+/// Textures are uploaded outside of painting as well, so the active texture unit and the texture that was
+/// bound to unit 0 are restored afterwards, as X-Plane keeps track of them itself.
+unsafe fn with_bound_texture(gl: &glow::Context, tex: NativeTexture, upload: impl FnOnce()) {
+    let active_texture = gl.get_parameter_i32(glow::ACTIVE_TEXTURE) as u32;
+    gl.active_texture(glow::TEXTURE0);
+    let previous = NonZeroU32::new(gl.get_parameter_i32(glow::TEXTURE_BINDING_2D) as u32);
+    gl.bind_texture(glow::TEXTURE_2D, Some(tex));
+    upload();
+    gl.bind_texture(glow::TEXTURE_2D, previous.map(NativeTexture));
+    gl.active_texture(active_texture);
 }
 
 pub(crate) unsafe fn as_u8_slice<T>(s: &[T]) -> &[u8] {
-    std::slice::from_raw_parts(s.as_ptr().cast::<u8>(), s.len() * std::mem::size_of::<T>())
+    std::slice::from_raw_parts(s.as_ptr().cast::<u8>(), std::mem::size_of_val(s))
 }
 
-pub fn check_gl_error(gl: &glow::Context, description: impl std::fmt::Display) {
-    loop {
-        let err = unsafe { gl.get_error() };
-        match err {
-            glow::NO_ERROR => break,
-            _ => debugln!("gl error {description}: {err:x}"),
+/// What [`check_gl_error`] does with OpenGL errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlErrorPolicy {
+    /// Don't even ask OpenGL for errors. The default in release builds.
+    Ignore,
+    /// Log every error to Log.txt.
+    Log,
+    /// Log each distinct error only once. The default in debug builds.
+    LogOnce,
+    /// Keep the errors for [`take_gl_errors`].
+    Collect,
+    /// Panic on the first error. Note that this takes down X-Plane as well.
+    ///
+    /// A panic can't unwind out of the debug callback, so messages of the
+    /// [debug output](crate::XplmGuiContext::enable_gl_debug_output) panic once painting is done.
+    Panic,
+}
+
+impl Default for GlErrorPolicy {
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            GlErrorPolicy::LogOnce
+        } else {
+            GlErrorPolicy::Ignore
+        }
+    }
+}
+
+/// At most this many errors are kept with [`GlErrorPolicy::Collect`].
+const MAX_COLLECTED_GL_ERRORS: usize = 1000;
+
+#[derive(Default)]
+struct GlErrors {
+    policy: GlErrorPolicy,
+    logged: HashSet<String>,
+    collected: Vec<Error>,
+    /// Our debug callback is installed and reports the errors, so there is no need to poll.
+    debug_output_installed: bool,
+    /// The first error of the debug callback with [`GlErrorPolicy::Panic`].
+    deferred_panic: Option<Error>,
+}
+
+thread_local! {
+    // X-Plane calls plugins from its main thread only
+    static GL_ERRORS: RefCell<GlErrors> = RefCell::new(GlErrors::default());
+    /// Set from the backend of the last created context, only logs to stderr before that.
+    static LOGGER: Cell<fn(&str)> = Cell::new(|message| eprintln!("{message}"));
+}
+
+pub(crate) fn set_logger(logger: fn(&str)) {
+    LOGGER.with(|current| current.set(logger));
+}
+
+/// Use the `log!` macro instead.
+pub(crate) fn log(message: &str) {
+    LOGGER.with(|logger| logger.get()(message));
+}
+
+/// Sets what happens to OpenGL errors found by [`check_gl_error`] and the debug output
+/// enabled with [`crate::XplmGuiContext::enable_gl_debug_output`].
+pub fn set_gl_error_policy(policy: GlErrorPolicy) {
+    GL_ERRORS.with(|errors| errors.borrow_mut().policy = policy);
+}
+
+pub fn gl_error_policy() -> GlErrorPolicy {
+    GL_ERRORS.with(|errors| errors.borrow().policy)
+}
+
+/// Returns and forgets the errors collected with [`GlErrorPolicy::Collect`].
+pub fn take_gl_errors() -> Vec<Error> {
+    GL_ERRORS.with(|errors| std::mem::take(&mut errors.borrow_mut().collected))
+}
+
+pub(crate) fn report_gl_error(err: Error) {
+    if let Some(err) = handle_gl_error(err) {
+        panic!("{err}");
+    }
+}
+
+/// Like [`report_gl_error`], for the debug callback.
+///
+/// Unwinding out of an `extern "system"` function aborts the process, so with [`GlErrorPolicy::Panic`]
+/// the error is kept for [`panic_on_deferred_gl_error`] instead.
+pub(crate) fn report_gl_debug_message(err: Error) {
+    if let Some(err) = handle_gl_error(err) {
+        GL_ERRORS.with(|errors| {
+            let mut errors = errors.borrow_mut();
+            if errors.deferred_panic.is_none() {
+                errors.deferred_panic = Some(err);
+            }
+        });
+    }
+}
+
+/// Panics with the error kept by [`report_gl_debug_message`], if any.
+pub(crate) fn panic_on_deferred_gl_error() {
+    // Not panicking while GL_ERRORS is borrowed
+    if let Some(err) = GL_ERRORS.with(|errors| errors.borrow_mut().deferred_panic.take()) {
+        panic!("{err}");
+    }
+}
+
+pub(crate) fn set_debug_output_installed(installed: bool) {
+    GL_ERRORS.with(|errors| errors.borrow_mut().debug_output_installed = installed);
+}
+
+/// Handles the error according to the policy, and returns it if it is fatal.
+fn handle_gl_error(err: Error) -> Option<Error> {
+    GL_ERRORS.with(|errors| {
+        let mut errors = errors.borrow_mut();
+        match errors.policy {
+            GlErrorPolicy::Ignore => None,
+            GlErrorPolicy::Log => {
+                log!("{err}");
+                None
+            }
+            GlErrorPolicy::LogOnce => {
+                let message = err.to_string();
+                if !errors.logged.contains(&message) {
+                    log!("{message} (further occurrences are not logged)");
+                    errors.logged.insert(message);
+                }
+                None
+            }
+            GlErrorPolicy::Collect => {
+                if errors.collected.len() < MAX_COLLECTED_GL_ERRORS {
+                    errors.collected.push(err);
+                }
+                None
+            }
+            GlErrorPolicy::Panic => Some(err),
         }
+    })
+}
+
+/// Handles all pending OpenGL errors according to the [`GlErrorPolicy`].
+///
+/// While the [debug output](crate::XplmGuiContext::enable_gl_debug_output) is installed, it reports
+/// the errors and `glGetError` is not asked.
+pub fn check_gl_error(gl: &glow::Context, description: impl std::fmt::Display) {
+    let skip = GL_ERRORS.with(|errors| {
+        let errors = errors.borrow();
+        errors.policy == GlErrorPolicy::Ignore || errors.debug_output_installed
+    });
+    if skip {
+        return;
+    }
+    while let Err(err) = gl_error(gl, &description) {
+        report_gl_error(err);
+    }
+}
+
+/// Returns the next pending OpenGL error, if any.
+pub fn gl_error(gl: &glow::Context, description: impl std::fmt::Display) -> Result<(), Error> {
+    match unsafe { gl.get_error() } {
+        glow::NO_ERROR => Ok(()),
+        code => Err(Error::Gl {
+            description: description.to_string(),
+            code,
+        }),
     }
 }
 
 pub(crate) unsafe fn compile_shader(
     gl: &glow::Context,
-    shader_type: u32,
+    stage: ShaderStage,
     source: &str,
-) -> Result<glow::Shader, String> {
-    let shader = gl.create_shader(shader_type)?;
+) -> Result<glow::Shader, Error> {
+    let shader = gl
+        .create_shader(stage.gl_type())
+        .map_err(|err| Error::ShaderCreation { stage, err })?;
 
     gl.shader_source(shader, source);
 
@@ -82,15 +338,18 @@ pub(crate) unsafe fn compile_shader(
     if gl.get_shader_compile_status(shader) {
         Ok(shader)
     } else {
-        Err(gl.get_shader_info_log(shader))
+        Err(Error::ShaderCompile {
+            stage,
+            log: gl.get_shader_info_log(shader),
+        })
     }
 }
 
 pub(crate) unsafe fn link_program<'a, T: IntoIterator<Item = &'a glow::Shader>>(
     gl: &glow::Context,
     shaders: T,
-) -> Result<glow::Program, String> {
-    let program = gl.create_program()?;
+) -> Result<glow::Program, Error> {
+    let program = gl.create_program().map_err(Error::ProgramCreation)?;
 
     for shader in shaders {
         gl.attach_shader(program, *shader);
@@ -101,6 +360,72 @@ pub(crate) unsafe fn link_program<'a, T: IntoIterator<Item = &'a glow::Shader>>(
     if gl.get_program_link_status(program) {
         Ok(program)
     } else {
-        Err(gl.get_program_info_log(program))
+        Err(Error::ProgramLink(gl.get_program_info_log(program)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn debug_message() -> Error {
+        Error::GlDebugMessage {
+            source: glow::DEBUG_SOURCE_API,
+            kind: glow::DEBUG_TYPE_ERROR,
+            id: 1,
+            severity: glow::DEBUG_SEVERITY_HIGH,
+            message: "bad call".into(),
+        }
+    }
+
+    #[test]
+    fn texture_data_must_fill_the_size() {
+        assert!(check_texture_data([2, 3], 24).is_ok());
+        assert!(matches!(check_texture_data([0, 3], 0), Err(Error::TextureSize([0, 3]))));
+        assert!(matches!(
+            check_texture_data([2, 3], 20),
+            Err(Error::TextureDataLength { len: 20, .. })
+        ));
+        assert!(matches!(
+            check_texture_data([usize::MAX, 2], 8),
+            Err(Error::TextureDataLength { .. })
+        ));
+    }
+
+    #[test]
+    fn debug_messages_are_reported_while_polling_is_skipped() {
+        set_gl_error_policy(GlErrorPolicy::Collect);
+        set_debug_output_installed(true);
+        report_gl_debug_message(debug_message());
+        set_debug_output_installed(false);
+        let errors = take_gl_errors();
+        assert!(matches!(errors[..], [Error::GlDebugMessage { id: 1, .. }]), "{errors:?}");
+    }
+
+    #[test]
+    #[should_panic(expected = "bad call")]
+    fn debug_messages_panic_after_the_callback() {
+        set_gl_error_policy(GlErrorPolicy::Panic);
+        // Must not panic in the callback
+        report_gl_debug_message(debug_message());
+        panic_on_deferred_gl_error();
+    }
+
+    /// An error that is only found by polling is left to the debug output while it is installed.
+    #[cfg(feature = "offscreen")]
+    #[test]
+    fn no_polling_while_the_debug_output_is_installed() {
+        let context = crate::offscreen::OffscreenContext::new(4, 4, false).unwrap();
+        let gl = context.gl();
+        set_gl_error_policy(GlErrorPolicy::Collect);
+
+        set_debug_output_installed(true);
+        unsafe { gl.enable(0xdead) };
+        check_gl_error(gl, "while installed");
+        assert!(take_gl_errors().is_empty());
+
+        set_debug_output_installed(false);
+        check_gl_error(gl, "after uninstalling");
+        assert_eq!(take_gl_errors().len(), 1);
     }
 }
@@ -0,0 +1,88 @@
+//! Stand-ins for X-Plane, so the gui can be exercised in `cargo test` without a running sim.
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use xplm::geometry::Rect;
+
+use crate::backend::{SimBackend, WindowBackend};
+
+/// A window that only remembers its geometry and whether it has the keyboard focus.
+pub struct MockWindow {
+    geometry: RefCell<Rect<i32>>,
+    keyboard_focus: Cell<bool>,
+}
+
+impl MockWindow {
+    pub fn new(geometry: Rect<i32>) -> Self {
+        Self {
+            geometry: RefCell::new(geometry),
+            keyboard_focus: Cell::new(false),
+        }
+    }
+
+    pub fn set_geometry(&self, geometry: Rect<i32>) {
+        *self.geometry.borrow_mut() = geometry;
+    }
+
+    pub fn has_keyboard_focus(&self) -> bool {
+        self.keyboard_focus.get()
+    }
+}
+
+impl WindowBackend for MockWindow {
+    fn geometry(&self) -> Rect<i32> {
+        *self.geometry.borrow()
+    }
+
+    fn take_keyboard_focus(&self) {
+        self.keyboard_focus.set(true);
+    }
+
+    fn release_keyboard_focus(&self) {
+        self.keyboard_focus.set(false);
+    }
+}
+
+/// A sim with a settable user interface scale, which counts how often the graphics state was set.
+///
+/// Clones share their state, so keep a clone to inspect or change the backend after handing it
+/// to [`crate::XplmGuiContext::with_backend`].
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    state: Rc<MockBackendState>,
+}
+
+#[derive(Debug, Default)]
+struct MockBackendState {
+    ui_scale: Cell<Option<f32>>,
+    graphics_state_calls: Cell<usize>,
+}
+
+impl MockBackend {
+    pub fn set_ui_scale(&self, ui_scale: Option<f32>) {
+        self.state.ui_scale.set(ui_scale);
+    }
+
+    /// How often the painter prepared the graphics state, i.e. how often it painted.
+    pub fn graphics_state_calls(&self) -> usize {
+        self.state.graphics_state_calls.get()
+    }
+}
+
+impl SimBackend for MockBackend {
+    fn ui_scale(&mut self) -> Option<f32> {
+        self.state.ui_scale.get()
+    }
+
+    fn set_graphics_state(&self) {
+        let calls = &self.state.graphics_state_calls;
+        calls.set(calls.get() + 1);
+    }
+
+    fn log(message: &str) {
+        eprintln!("{message}");
+    }
+}
@@ -0,0 +1,434 @@
+//! Renders meshes without X-Plane or a display, with a surfaceless EGL context (e.g. Mesa's software
+//! renderer llvmpipe), so the visual output of the painter can be compared against golden images.
+
+#![allow(unsafe_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    ptr,
+};
+
+use egui::{epaint::Mesh, ClippedMesh, Color32, Pos2, Rect};
+use glow::HasContext;
+use khronos_egl as egl;
+
+use crate::{mock::MockBackend, painter::Painter, ColorPipeline, Error};
+
+/// `EGL_PLATFORM_SURFACELESS_MESA` of the `EGL_MESA_platform_surfaceless` extension.
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+/// The width and height of the canned scenes and their golden images.
+pub const GOLDEN_SIZE: u32 = 64;
+/// Allows for rounding differences between Mesa versions.
+pub const GOLDEN_TOLERANCE: u8 = 2;
+
+/// An OpenGL context without a window that renders into a framebuffer object of a fixed size.
+///
+/// The context is made current on the creating thread and stays current until it is dropped.
+pub struct OffscreenContext {
+    gl: glow::Context,
+    framebuffer: glow::Framebuffer,
+    renderbuffer: glow::Renderbuffer,
+    width: u32,
+    height: u32,
+    egl: egl::DynamicInstance<egl::EGL1_5>,
+    display: egl::Display,
+    context: egl::Context,
+}
+
+impl OffscreenContext {
+    /// Creates a compatibility profile context like X-Plane's. With `srgb`, the framebuffer stores sRGB,
+    /// so that `FRAMEBUFFER_SRGB` takes effect.
+    pub fn new(width: u32, height: u32, srgb: bool) -> Result<Self, Error> {
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required() }
+            .map_err(|err| Error::ContextCreation(err.to_string()))?;
+        let display = unsafe {
+            egl.get_platform_display(
+                PLATFORM_SURFACELESS_MESA,
+                egl::DEFAULT_DISPLAY,
+                &[egl::ATTRIB_NONE],
+            )
+        }
+        .map_err(egl_error)?;
+        egl.initialize(display).map_err(egl_error)?;
+        let context = create_context(&egl, display).inspect_err(|_| {
+            let _ = egl.terminate(display);
+        })?;
+        let gl = unsafe {
+            glow::Context::from_loader_function(|name| {
+                egl.get_proc_address(name)
+                    .map_or(ptr::null(), |f| f as *const _)
+            })
+        };
+        let framebuffer = unsafe { create_framebuffer(&gl, width, height, srgb) };
+        let (framebuffer, renderbuffer) = framebuffer.inspect_err(|_| {
+            let _ = egl.terminate(display);
+        })?;
+        Ok(Self {
+            gl,
+            framebuffer,
+            renderbuffer,
+            width,
+            height,
+            egl,
+            display,
+            context,
+        })
+    }
+
+    pub fn gl(&self) -> &glow::Context {
+        &self.gl
+    }
+
+    /// Loads another [`glow::Context`] for the same OpenGL context, e.g. to hand it to
+    /// [`crate::XplmGuiContext::with_backend`].
+    pub fn load_gl(&self) -> glow::Context {
+        unsafe {
+            glow::Context::from_loader_function(|name| {
+                self.egl
+                    .get_proc_address(name)
+                    .map_or(ptr::null(), |f| f as *const _)
+            })
+        }
+    }
+
+    /// The framebuffer as a window in X-Plane's coordinates, with a ui scale of 1.
+    pub fn window(&self) -> xplm::geometry::Rect<i32> {
+        xplm::geometry::Rect::from_left_top_right_bottom(
+            0,
+            self.height as i32,
+            self.width as i32,
+            0,
+        )
+    }
+
+    /// Clears the framebuffer to opaque black.
+    pub fn clear(&self) {
+        unsafe {
+            self.gl
+                .viewport(0, 0, self.width as i32, self.height as i32);
+            self.gl.clear_color(0.0, 0.0, 0.0, 1.0);
+            self.gl.clear(glow::COLOR_BUFFER_BIT);
+        }
+    }
+
+    /// Reads back the framebuffer, with the first row at the top.
+    pub fn read_pixels(&self) -> image::RgbaImage {
+        let mut pixels = vec![0; self.width as usize * self.height as usize * 4];
+        unsafe {
+            self.gl.read_pixels(
+                0,
+                0,
+                self.width as i32,
+                self.height as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(&mut pixels),
+            );
+        }
+        let image = image::RgbaImage::from_raw(self.width, self.height, pixels)
+            .expect("buffer has the size of the image");
+        // OpenGL rows start at the bottom
+        image::imageops::flip_vertical(&image)
+    }
+}
+
+impl Drop for OffscreenContext {
+    fn drop(&mut self) {
+        unsafe {
+            self.gl.delete_framebuffer(self.framebuffer);
+            self.gl.delete_renderbuffer(self.renderbuffer);
+        }
+        let _ = self.egl.make_current(self.display, None, None, None);
+        let _ = self.egl.destroy_context(self.display, self.context);
+        let _ = self.egl.terminate(self.display);
+    }
+}
+
+fn egl_error(err: egl::Error) -> Error {
+    Error::ContextCreation(err.to_string())
+}
+
+fn create_context(
+    egl: &egl::DynamicInstance<egl::EGL1_5>,
+    display: egl::Display,
+) -> Result<egl::Context, Error> {
+    egl.bind_api(egl::OPENGL_API).map_err(egl_error)?;
+    let config = egl
+        .choose_first_config(
+            display,
+            &[
+                egl::SURFACE_TYPE,
+                egl::PBUFFER_BIT,
+                egl::RENDERABLE_TYPE,
+                egl::OPENGL_BIT,
+                egl::NONE,
+            ],
+        )
+        .map_err(egl_error)?
+        .ok_or_else(|| Error::ContextCreation("no OpenGL config".to_owned()))?;
+    let context = egl
+        .create_context(display, config, None, &[egl::NONE])
+        .map_err(egl_error)?;
+    egl.make_current(display, None, None, Some(context))
+        .map_err(egl_error)?;
+    Ok(context)
+}
+
+unsafe fn create_framebuffer(
+    gl: &glow::Context,
+    width: u32,
+    height: u32,
+    srgb: bool,
+) -> Result<(glow::Framebuffer, glow::Renderbuffer), Error> {
+    let format = if srgb {
+        glow::SRGB8_ALPHA8
+    } else {
+        glow::RGBA8
+    };
+    let renderbuffer = gl.create_renderbuffer().map_err(Error::ContextCreation)?;
+    gl.bind_renderbuffer(glow::RENDERBUFFER, Some(renderbuffer));
+    gl.renderbuffer_storage(glow::RENDERBUFFER, format, width as i32, height as i32);
+    let framebuffer = gl.create_framebuffer().map_err(Error::ContextCreation)?;
+    gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+    gl.framebuffer_renderbuffer(
+        glow::FRAMEBUFFER,
+        glow::COLOR_ATTACHMENT0,
+        glow::RENDERBUFFER,
+        Some(renderbuffer),
+    );
+    let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+    if status != glow::FRAMEBUFFER_COMPLETE {
+        return Err(Error::ContextCreation(format!(
+            "incomplete framebuffer: {status:x}"
+        )));
+    }
+    Ok((framebuffer, renderbuffer))
+}
+
+/// An [`OffscreenContext`] with a [`Painter`] that paints into it.
+pub struct OffscreenRenderer {
+    painter: Painter,
+    backend: MockBackend,
+    context: OffscreenContext,
+}
+
+impl OffscreenRenderer {
+    /// For [`ColorPipeline::Linear`], the framebuffer stores sRGB, like the pipeline expects.
+    pub fn new(width: u32, height: u32, color_pipeline: ColorPipeline) -> Result<Self, Error> {
+        let context =
+            OffscreenContext::new(width, height, color_pipeline == ColorPipeline::Linear)?;
+        let gl = context.gl();
+        let mut painter = Painter::new(gl, None, "", color_pipeline)?;
+
+        // The canned meshes only use the white pixel of the font texture
+        let font_image = egui::FontImage {
+            version: 0,
+            width: 1,
+            height: 1,
+            pixels: vec![255],
+        };
+        painter.upload_egui_texture(gl, &font_image)?;
+
+        Ok(Self {
+            painter,
+            backend: MockBackend::default(),
+            context,
+        })
+    }
+
+    /// Clears to opaque black, paints the meshes and reads back the result.
+    pub fn render(
+        &mut self,
+        pixels_per_point: f32,
+        clipped_meshes: &[ClippedMesh],
+    ) -> image::RgbaImage {
+        self.context.clear();
+        // Done by X-Plane's graphics state in the sim
+        unsafe { self.context.gl().enable(glow::BLEND) };
+        self.painter.paint_meshes(
+            self.context.gl(),
+            &self.backend,
+            self.context.window(),
+            1.0,
+            pixels_per_point,
+            clipped_meshes,
+        );
+        self.context.read_pixels()
+    }
+}
+
+impl Drop for OffscreenRenderer {
+    fn drop(&mut self) {
+        self.painter.destroy(self.context.gl());
+    }
+}
+
+/// How much two images differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageDiff {
+    /// Pixels where a channel differs by more than the tolerance.
+    pub differing_pixels: usize,
+    /// The largest difference of a single channel.
+    pub max_difference: u8,
+}
+
+/// Compares two images of the same size channel by channel.
+pub fn compare(actual: &image::RgbaImage, expected: &image::RgbaImage, tolerance: u8) -> ImageDiff {
+    assert_eq!(actual.dimensions(), expected.dimensions());
+    let mut diff = ImageDiff {
+        differing_pixels: 0,
+        max_difference: 0,
+    };
+    for (a, e) in actual.pixels().zip(expected.pixels()) {
+        let difference =
+            a.0.iter()
+                .zip(e.0.iter())
+                .map(|(a, e)| a.abs_diff(*e))
+                .max()
+                .unwrap_or(0);
+        diff.max_difference = diff.max_difference.max(difference);
+        if difference > tolerance {
+            diff.differing_pixels += 1;
+        }
+    }
+    diff
+}
+
+/// The outcome of [`check_golden`].
+#[derive(Debug)]
+pub enum GoldenResult {
+    /// The image matches the golden image within the tolerance.
+    Match(ImageDiff),
+    /// The image differs from the golden image.
+    Mismatch(ImageDiff),
+    /// The golden image has a different size, which is a mismatch as well.
+    SizeMismatch { expected: (u32, u32) },
+    /// There is no golden image yet.
+    Missing,
+    /// The golden image was (re)written.
+    Updated,
+}
+
+/// Compares `actual` against the PNG at `path`, or overwrites it with `actual` if `update` is set.
+pub fn check_golden(
+    path: &Path,
+    actual: &image::RgbaImage,
+    tolerance: u8,
+    update: bool,
+) -> image::ImageResult<GoldenResult> {
+    if update {
+        actual.save(path)?;
+        return Ok(GoldenResult::Updated);
+    }
+    if !path.exists() {
+        return Ok(GoldenResult::Missing);
+    }
+    let expected = image::open(path)?.to_rgba8();
+    if expected.dimensions() != actual.dimensions() {
+        return Ok(GoldenResult::SizeMismatch {
+            expected: expected.dimensions(),
+        });
+    }
+    let diff = compare(actual, &expected, tolerance);
+    if diff.differing_pixels == 0 {
+        Ok(GoldenResult::Match(diff))
+    } else {
+        Ok(GoldenResult::Mismatch(diff))
+    }
+}
+
+/// One canned scene checked against its golden image by [`check_goldens`].
+pub struct GoldenCheck {
+    pub path: PathBuf,
+    pub actual: image::RgbaImage,
+    pub result: GoldenResult,
+}
+
+impl GoldenCheck {
+    /// Saves the rendered image next to the golden image, to look at a failed check.
+    pub fn save_actual(&self) -> image::ImageResult<PathBuf> {
+        let actual_path = self.path.with_extension("actual.png");
+        self.actual.save(&actual_path)?;
+        Ok(actual_path)
+    }
+}
+
+/// Renders the [`canned_scenes`] with both color pipelines and compares them against the
+/// `<scene>_<gamma|linear>.png` images in `golden_dir` with [`check_golden`].
+pub fn check_goldens(golden_dir: &Path, update: bool) -> Result<Vec<GoldenCheck>, Error> {
+    let mut checks = Vec::new();
+    for (pipeline, suffix) in [
+        (ColorPipeline::GammaSpace, "gamma"),
+        (ColorPipeline::Linear, "linear"),
+    ] {
+        let mut renderer = OffscreenRenderer::new(GOLDEN_SIZE, GOLDEN_SIZE, pipeline)?;
+        for (name, meshes) in canned_scenes() {
+            let actual = renderer.render(1.0, &meshes);
+            let path = golden_dir.join(format!("{name}_{suffix}.png"));
+            let result = check_golden(&path, &actual, GOLDEN_TOLERANCE, update)
+                .map_err(|err| Error::GoldenImage(format!("{}: {err}", path.display())))?;
+            checks.push(GoldenCheck {
+                path,
+                actual,
+                result,
+            });
+        }
+    }
+    Ok(checks)
+}
+
+/// Meshes that cover clipping, blending and vertex colors, named after their golden image,
+/// for a window of [`GOLDEN_SIZE`] points.
+pub fn canned_scenes() -> Vec<(&'static str, Vec<ClippedMesh>)> {
+    let full = Rect::from_min_max(Pos2::new(0.0, 0.0), Pos2::new(64.0, 64.0));
+    let rect = |min: (f32, f32), max: (f32, f32), color: Color32| {
+        let mut mesh = Mesh::default();
+        mesh.add_colored_rect(Rect::from_min_max(min.into(), max.into()), color);
+        mesh
+    };
+
+    let mut gradient = Mesh::default();
+    gradient.add_colored_rect(full, Color32::BLACK);
+    gradient.vertices[1].color = Color32::WHITE;
+    gradient.vertices[3].color = Color32::WHITE;
+
+    vec![
+        (
+            "solid",
+            vec![ClippedMesh(
+                full,
+                rect((8.0, 8.0), (56.0, 56.0), Color32::from_rgb(200, 40, 40)),
+            )],
+        ),
+        (
+            "clipped",
+            vec![ClippedMesh(
+                Rect::from_min_max(Pos2::new(16.0, 4.0), Pos2::new(40.0, 32.0)),
+                rect((0.0, 0.0), (64.0, 64.0), Color32::from_rgb(40, 200, 40)),
+            )],
+        ),
+        (
+            "blending",
+            vec![
+                ClippedMesh(
+                    full,
+                    rect(
+                        (4.0, 4.0),
+                        (44.0, 44.0),
+                        Color32::from_rgba_unmultiplied(255, 0, 0, 128),
+                    ),
+                ),
+                ClippedMesh(
+                    full,
+                    rect(
+                        (20.0, 20.0),
+                        (60.0, 60.0),
+                        Color32::from_rgba_unmultiplied(0, 0, 255, 128),
+                    ),
+                ),
+            ],
+        ),
+        ("gradient", vec![ClippedMesh(full, gradient)]),
+    ]
+}
@@ -1,27 +1,83 @@
 #![allow(unsafe_code)]
 
-use std::collections::HashMap;
+use std::{collections::HashMap, num::NonZeroU32};
 
 use egui::epaint::{Mesh, Vertex};
 use glow::{HasContext, NativeTexture};
 use memoffset::offset_of;
 
 use crate::{
+    backend::SimBackend,
     check_gl_error,
-    misc_util::{as_u8_slice, compile_shader, link_program, srgbtexture2d},
+    misc_util::gl_error,
+    shader_version::ShaderVersion,
+    Error, ShaderStage,
+    misc_util::{
+        as_u8_slice, compile_shader, link_program, srgbtexture2d, update_srgbtexture2d,
+    },
 };
 
-pub use glow::Context;
-
 const VERT_SRC: &str = include_str!("shader.vert");
 const FRAG_SRC: &str = include_str!("shader.frag");
 
+/// epi hands out user texture ids counting up from zero, ours start far above them.
+const FIRST_USER_TEXTURE_ID: u64 = 1 << 32;
+
+/// How colors are blended into X-Plane's framebuffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorPipeline {
+    /// Blend in gamma space, like the rest of X-Plane's ui.
+    ///
+    /// Textures are stored as plain RGBA, vertex colors are used as they are,
+    /// and the font is uploaded with a gamma of 1/2.2 to keep text crisp. This is the default.
+    #[default]
+    GammaSpace,
+    /// Blend in linear space, which is what egui is designed for.
+    ///
+    /// Textures are stored as sRGB, vertex colors are decoded in the shader, and `FRAMEBUFFER_SRGB`
+    /// is enabled while painting, so the output is encoded back to sRGB.
+    Linear,
+}
+
+impl ColorPipeline {
+    fn texture_format(self) -> u32 {
+        match self {
+            ColorPipeline::GammaSpace => glow::RGBA8,
+            ColorPipeline::Linear => glow::SRGB8_ALPHA8,
+        }
+    }
+
+    fn shader_define(self) -> &'static str {
+        match self {
+            ColorPipeline::GammaSpace => "",
+            ColorPipeline::Linear => "#define LINEAR_COLOR_PIPELINE\n",
+        }
+    }
+}
+
+/// Who is responsible for deleting a texture registered with [`Painter::register_native_texture`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureOwnership {
+    /// The texture belongs to someone else (X-Plane, another plugin, ...) and is never deleted by the painter.
+    Borrowed,
+    /// The painter deletes the texture once it is freed or the painter is destroyed.
+    Owned,
+}
+
+struct UserTexture {
+    texture: glow::Texture,
+    ownership: TextureOwnership,
+    /// Known for the textures the painter uploaded itself.
+    size: Option<[usize; 2]>,
+}
+
 /// OpenGL painter
 ///
 /// This struct must be destroyed with [`Painter::destroy`] before dropping, to ensure OpenGL
 /// objects have been properly deleted and are not leaked.
 pub struct Painter {
     program: glow::Program,
+    color_pipeline: ColorPipeline,
     u_screen_size: glow::UniformLocation,
     u_sampler: glow::UniformLocation,
     egui_texture: Option<NativeTexture>,
@@ -31,10 +87,10 @@ pub struct Painter {
     element_array_buffer: glow::Buffer,
 
     /// Index is the same as in [`egui::TextureId::User`].
-    user_textures: HashMap<u64, glow::Texture>,
+    user_textures: HashMap<u64, UserTexture>,
 
-    #[cfg(feature = "epi")]
-    next_native_tex_id: u64, // TODO: 128-bit texture space?
+    /// Starts at [`FIRST_USER_TEXTURE_ID`].
+    next_user_texture_id: u64,
 
     /// Stores outdated OpenGL textures that are yet to be deleted
     textures_to_destroy: Vec<glow::Texture>,
@@ -46,37 +102,58 @@ pub struct Painter {
 impl Painter {
     /// Create painter.
     ///
-    /// Set `pp_fb_extent` to the framebuffer size to enable `sRGB` support on OpenGL ES and WebGL.
+    /// The shaders are compiled as `shader_version`, or the newest version the context supports if `None`.
+    /// Colors are blended according to `color_pipeline`.
     ///
     /// Set `shader_prefix` if you want to turn on shader workaround e.g. `"#define APPLY_BRIGHTENING_GAMMA\n"`
     /// (see <https://github.com/emilk/egui/issues/794>).
     ///
     /// # Errors
     /// will return `Err` below cases
-    /// * failed to compile shader
-    /// * failed to create postprocess on webgl with `sRGB` support
-    /// * failed to create buffer
+    /// * failed to compile or link shader
+    /// * failed to create buffer or vertex array
+    /// * an OpenGL error occurred during setup
     pub fn new(
         gl: &glow::Context,
         //pp_fb_extent: Option<[i32; 2]>,
-    ) -> Result<Painter, String> {
+        shader_version: Option<ShaderVersion>,
+        shader_prefix: &str,
+        color_pipeline: ColorPipeline,
+    ) -> Result<Painter, Error> {
+        let shader_version = shader_version.unwrap_or_else(|| ShaderVersion::get(gl));
+        let shader_prefix = format!("{}{}", color_pipeline.shader_define(), shader_prefix);
+        let shader_prefix = shader_prefix.as_str();
+        let vert_src = shader_version.preprocess(shader_prefix, VERT_SRC);
+        let frag_src = shader_version.preprocess(shader_prefix, FRAG_SRC);
         unsafe {
-            let vert = compile_shader(gl, glow::VERTEX_SHADER, VERT_SRC)?;
-            let frag = compile_shader(gl, glow::FRAGMENT_SHADER, FRAG_SRC)?;
+            let vert = compile_shader(gl, ShaderStage::Vertex, &vert_src)?;
+            let frag = compile_shader(gl, ShaderStage::Fragment, &frag_src)?;
             let program = link_program(gl, [vert, frag].iter())?;
             gl.detach_shader(program, vert);
             gl.detach_shader(program, frag);
             gl.delete_shader(vert);
             gl.delete_shader(frag);
-            let u_screen_size = gl.get_uniform_location(program, "u_screen_size").unwrap();
-            let u_sampler = gl.get_uniform_location(program, "u_sampler").unwrap();
-            let vertex_buffer = gl.create_buffer()?;
-            let element_array_buffer = gl.create_buffer()?;
+            let u_screen_size = gl
+                .get_uniform_location(program, "u_screen_size")
+                .ok_or(Error::MissingShaderInput("u_screen_size"))?;
+            let u_sampler = gl
+                .get_uniform_location(program, "u_sampler")
+                .ok_or(Error::MissingShaderInput("u_sampler"))?;
+            let vertex_buffer = gl.create_buffer().map_err(Error::BufferCreation)?;
+            let element_array_buffer = gl.create_buffer().map_err(Error::BufferCreation)?;
             gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
-            let a_pos_loc = gl.get_attrib_location(program, "a_pos").unwrap();
-            let a_tc_loc = gl.get_attrib_location(program, "a_tc").unwrap();
-            let a_srgba_loc = gl.get_attrib_location(program, "a_srgba").unwrap();
-            let mut vertex_array = gl.create_vertex_array()?;
+            let a_pos_loc = gl
+                .get_attrib_location(program, "a_pos")
+                .ok_or(Error::MissingShaderInput("a_pos"))?;
+            let a_tc_loc = gl
+                .get_attrib_location(program, "a_tc")
+                .ok_or(Error::MissingShaderInput("a_tc"))?;
+            let a_srgba_loc = gl
+                .get_attrib_location(program, "a_srgba")
+                .ok_or(Error::MissingShaderInput("a_srgba"))?;
+            let vertex_array = gl
+                .create_vertex_array()
+                .map_err(Error::VertexArrayCreation)?;
             gl.bind_vertex_array(Some(vertex_array));
             gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
             let stride = std::mem::size_of::<Vertex>() as i32;
@@ -108,10 +185,11 @@ impl Painter {
             gl.enable_vertex_attrib_array(a_tc_loc);
             gl.enable_vertex_attrib_array(a_srgba_loc);
 
-            check_gl_error(gl, "while setting up painter");
+            gl_error(gl, "while setting up painter")?;
 
             Ok(Painter {
                 program,
+                color_pipeline,
                 u_screen_size,
                 u_sampler,
                 egui_texture: None,
@@ -120,63 +198,73 @@ impl Painter {
                 vertex_buffer,
                 element_array_buffer,
                 user_textures: Default::default(),
+                next_user_texture_id: FIRST_USER_TEXTURE_ID,
                 textures_to_destroy: Vec::new(),
                 destroyed: false,
             })
         }
     }
 
-    pub fn upload_egui_texture(&mut self, gl: &glow::Context, font_image: &egui::FontImage) {
+    pub fn upload_egui_texture(
+        &mut self,
+        gl: &glow::Context,
+        font_image: &egui::FontImage,
+    ) -> Result<(), Error> {
         self.assert_not_destroyed();
 
         if self.egui_texture_version == Some(font_image.version) {
-            return; // No change
+            return Ok(()); // No change
         }
-        let gamma = 1.0;
+        let gamma = match self.color_pipeline {
+            ColorPipeline::GammaSpace => 1.0 / 2.2,
+            ColorPipeline::Linear => 1.0,
+        };
         let pixels: Vec<u8> = font_image
             .srgba_pixels(gamma)
             .flat_map(|a| Vec::from(a.to_array()))
             .collect();
 
-        if let Some(old_tex) = std::mem::replace(
-            &mut self.egui_texture,
-            Some(srgbtexture2d(
-                gl,
-                &pixels,
-                font_image.width,
-                font_image.height,
-            )),
-        ) {
+        if let Some(old_tex) = self.egui_texture.replace(srgbtexture2d(
+            gl,
+            self.color_pipeline.texture_format(),
+            &pixels,
+            font_image.width,
+            font_image.height,
+        )?) {
             unsafe {
                 //gl.delete_texture(texture_from_raw_id(old_tex));
                 gl.delete_texture(old_tex);
             }
         }
         self.egui_texture_version = Some(font_image.version);
+        Ok(())
     }
 
     unsafe fn prepare_painting(
         &mut self,
+        backend: &dyn SimBackend,
         window: xplm::geometry::Rect<i32>,
         gl: &glow::Context,
         pixels_per_point: f32,
-    ) -> (u32, u32) {
-        xplm::draw::set_state(&xplm::draw::GraphicsState {
-            fog: false,
-            lighting: false,
-            alpha_testing: true,
-            alpha_blending: true,
-            depth_testing: false,
-            depth_writing: false,
-            textures: 1, // TODO is this right?
-        });
+    ) -> GlState {
+        backend.set_graphics_state();
         check_gl_error(gl, "while setting xplm graphic options");
 
+        // Captured after set_state, so that the state X-Plane tracks itself stays as X-Plane expects it
+        let state = GlState::capture(gl);
+        check_gl_error(gl, "while capturing gl state");
+
         gl.enable(glow::SCISSOR_TEST);
         check_gl_error(gl, "while enabling scissor test");
         // egui outputs mesh in both winding orders
         gl.disable(glow::CULL_FACE);
         check_gl_error(gl, "while disabling face culling");
+        set_enabled(
+            gl,
+            glow::FRAMEBUFFER_SRGB,
+            self.color_pipeline == ColorPipeline::Linear,
+        );
+        check_gl_error(gl, "while setting framebuffer srgb");
 
         gl.blend_func_separate(
             // egui outputs colors with premultiplied alpha:
@@ -208,71 +296,79 @@ impl Painter {
         check_gl_error(gl, "while setting screen_size uniform");
         gl.uniform_1_i32(Some(&self.u_sampler), 0);
         check_gl_error(gl, "while setting sampler uniform");
-        if let Some(tex) = self.egui_texture {
-            //xplm::draw::bind_texture(0, tex);
-            gl.bind_texture(glow::TEXTURE_2D, Some(tex));
-            check_gl_error(gl, "while binding texture uniform");
-        }
+        gl.active_texture(glow::TEXTURE0);
+        check_gl_error(gl, "while selecting texture unit");
         gl.bind_vertex_array(Some(self.vertex_array));
         check_gl_error(gl, "while binding vao");
 
         gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(self.element_array_buffer));
         check_gl_error(gl, "while binding element buffer");
 
-        (width_in_pixels as u32, height_in_pixels as u32)
+        state
     }
 
-    unsafe fn cleanup_painting(
-        &mut self,
-        [left, bottom, right, top]: [i32; 4],
-        gl: &glow::Context,
-    ) {
-        gl.disable(glow::SCISSOR_TEST);
-        // egui outputs mesh in both winding orders
-        gl.enable(glow::CULL_FACE);
-
-        gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
-
-        gl.viewport(left, bottom, right - left, top - bottom);
-        gl.use_program(None);
-
-        gl.bind_vertex_array(None);
-
-        gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, None);
-
+    unsafe fn cleanup_painting(&mut self, state: GlState, gl: &glow::Context) {
+        state.restore(gl);
         check_gl_error(gl, "while cleaning up after painting");
     }
 
+    /// Paints the meshes into `inner_size`, the window geometry in X-Plane's global boxel coordinates.
+    ///
+    /// OpenGL works in framebuffer pixels, of which a boxel covers `pixels_per_boxel` (X-Plane's user
+    /// interface scale). All OpenGL state that is changed while painting is restored afterwards.
     pub fn paint_meshes(
         &mut self,
         gl: &glow::Context,
+        backend: &dyn SimBackend,
         inner_size: xplm::geometry::Rect<i32>,
-        viewport: [i32; 4],
+        pixels_per_boxel: f32,
         pixels_per_point: f32,
         clipped_meshes: &[egui::ClippedMesh],
     ) {
         self.assert_not_destroyed();
 
-        let size_in_pixels = unsafe { self.prepare_painting(inner_size, gl, pixels_per_point) };
+        let window = boxels_to_pixels(inner_size, pixels_per_boxel);
+        let state = unsafe { self.prepare_painting(backend, window, gl, pixels_per_point) };
         for egui::ClippedMesh(clip_rect, mesh) in clipped_meshes {
-            self.paint_mesh(gl, size_in_pixels, &inner_size, &mesh);
+            if let Some(scissor) = scissor_box(window, pixels_per_point, *clip_rect) {
+                self.paint_mesh(gl, scissor, mesh);
+            }
         }
         check_gl_error(gl, "while painting");
         unsafe {
-            self.cleanup_painting(viewport, gl);
+            self.cleanup_painting(state, gl);
+
+            for t in self.textures_to_destroy.drain(..) {
+                gl.delete_texture(t);
+            }
+        }
+    }
+
+    fn get_texture(&self, texture_id: egui::TextureId) -> Option<glow::Texture> {
+        match texture_id {
+            egui::TextureId::Egui => self.egui_texture,
+            egui::TextureId::User(id) => self.user_textures.get(&id).map(|t| t.texture),
         }
     }
 
     fn paint_mesh(
         &mut self,
         gl: &glow::Context,
-        size_in_pixels: (u32, u32),
-        clip_rect: &xplm::geometry::Rect<i32>,
+        [x, y, width, height]: [i32; 4],
         mesh: &Mesh,
     ) {
         debug_assert!(mesh.is_valid());
-        //TODO support user textures
+        let texture = match self.get_texture(mesh.texture_id) {
+            Some(texture) => texture,
+            None => {
+                log!("egui: no texture found for {:?}", mesh.texture_id);
+                return;
+            }
+        };
         unsafe {
+            //xplm::draw::bind_texture(0, tex);
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+
             gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.vertex_buffer));
             gl.buffer_data_u8_slice(
                 glow::ARRAY_BUFFER,
@@ -287,19 +383,8 @@ impl Painter {
                 glow::STREAM_DRAW,
             );
         }
-        // Transform clip rect to physical pixels:
-        let clip_min_x = clip_rect.left();
-        let clip_min_y = clip_rect.bottom();
-        let clip_max_x = clip_rect.right();
-        let clip_max_y = clip_rect.top();
-
         unsafe {
-            gl.scissor(
-                clip_min_x,
-                clip_min_y,
-                clip_max_x - clip_min_x,
-                clip_max_y - clip_min_y,
-            );
+            gl.scissor(x, y, width, height);
             gl.draw_elements(
                 glow::TRIANGLES,
                 mesh.indices.len() as i32,
@@ -309,8 +394,136 @@ impl Painter {
         }
     }
 
+    /// Uploads `srgba_pixels` (premultiplied, row by row from the top left) into a new texture
+    /// of `size` (width, height) and returns the [`egui::TextureId`] that can be used to show it.
+    pub fn alloc_user_texture(
+        &mut self,
+        gl: &glow::Context,
+        size: [usize; 2],
+        srgba_pixels: &[egui::Color32],
+    ) -> Result<egui::TextureId, Error> {
+        self.assert_not_destroyed();
+
+        let [w, h] = size;
+        let pixels = unsafe { as_u8_slice(srgba_pixels) };
+        let tex = srgbtexture2d(gl, self.color_pipeline.texture_format(), pixels, w, h)?;
+        Ok(self.insert_user_texture(UserTexture {
+            texture: tex,
+            ownership: TextureOwnership::Owned,
+            size: Some(size),
+        }))
+    }
+
+    /// Makes an existing OpenGL texture available to egui without copying it.
+    ///
+    /// Depending on `ownership`, the texture is deleted when it is freed again.
+    pub fn register_native_texture(
+        &mut self,
+        native: glow::Texture,
+        ownership: TextureOwnership,
+    ) -> egui::TextureId {
+        self.assert_not_destroyed();
+
+        self.insert_user_texture(UserTexture {
+            texture: native,
+            ownership,
+            size: None,
+        })
+    }
+
+    fn insert_user_texture(&mut self, texture: UserTexture) -> egui::TextureId {
+        let id = self.next_user_texture_id;
+        self.next_user_texture_id += 1;
+        self.user_textures.insert(id, texture);
+        egui::TextureId::User(id)
+    }
+
+    /// Lets an already registered [`egui::TextureId`] show a different OpenGL texture.
+    ///
+    /// The replaced texture is released according to its own ownership.
+    #[cfg(feature = "epi")]
+    fn replace_user_texture(&mut self, tex_id: egui::TextureId, replacing: UserTexture) {
+        if let egui::TextureId::User(id) = tex_id {
+            let native = replacing.texture;
+            if let Some(old_tex) = self.user_textures.insert(id, replacing) {
+                if old_tex.ownership == TextureOwnership::Owned && old_tex.texture != native {
+                    self.textures_to_destroy.push(old_tex.texture);
+                }
+            }
+        }
+    }
+
+    /// Overwrites the region starting at `pos` (x, y from the top left) with the given
+    /// `size` of a texture created with [`Painter::alloc_user_texture`].
+    ///
+    /// The region must lie within the texture. That is only checked for textures the painter
+    /// uploaded itself, as the size of [registered](Painter::register_native_texture) ones is unknown.
+    pub fn update_user_texture(
+        &mut self,
+        gl: &glow::Context,
+        tex_id: egui::TextureId,
+        pos: [usize; 2],
+        size: [usize; 2],
+        srgba_pixels: &[egui::Color32],
+    ) -> Result<(), Error> {
+        self.assert_not_destroyed();
+
+        let texture = match tex_id {
+            egui::TextureId::User(id) => self.user_textures.get(&id),
+            egui::TextureId::Egui => None,
+        }
+        .ok_or(Error::UnknownTexture(tex_id))?;
+        if let Some(texture_size) = texture.size {
+            let fits = |axis: usize| {
+                pos[axis]
+                    .checked_add(size[axis])
+                    .is_some_and(|end| end <= texture_size[axis])
+            };
+            if !fits(0) || !fits(1) {
+                return Err(Error::TextureRegion {
+                    pos,
+                    size,
+                    texture_size,
+                });
+            }
+        }
+        let pixels = unsafe { as_u8_slice(srgba_pixels) };
+        update_srgbtexture2d(gl, texture.texture, pos, size, pixels)
+    }
+
+    /// Uploads the pixels into a new texture shown for the user texture `tex_id`, which was
+    /// handed out by epi. A texture that was shown for `tex_id` before is deleted.
+    #[cfg(feature = "epi")]
+    pub fn set_user_texture(
+        &mut self,
+        gl: &glow::Context,
+        tex_id: u64,
+        size: [usize; 2],
+        srgba_pixels: &[egui::Color32],
+    ) -> Result<(), Error> {
+        self.assert_not_destroyed();
+
+        let [w, h] = size;
+        let pixels = unsafe { as_u8_slice(srgba_pixels) };
+        let tex = srgbtexture2d(gl, self.color_pipeline.texture_format(), pixels, w, h)?;
+        self.replace_user_texture(
+            egui::TextureId::User(tex_id),
+            UserTexture {
+                texture: tex,
+                ownership: TextureOwnership::Owned,
+                size: Some(size),
+            },
+        );
+        Ok(())
+    }
+
+    /// Frees a user texture. Owned OpenGL textures are deleted after the next paint.
     pub fn free_texture(&mut self, tex_id: u64) {
-        self.user_textures.remove(&tex_id);
+        if let Some(old_tex) = self.user_textures.remove(&tex_id) {
+            if old_tex.ownership == TextureOwnership::Owned {
+                self.textures_to_destroy.push(old_tex.texture);
+            }
+        }
     }
 
     unsafe fn destroy_gl(&self, gl: &glow::Context) {
@@ -320,8 +533,11 @@ impl Painter {
             gl.delete_texture(tex);
         }
         for tex in self.user_textures.values() {
-            gl.delete_texture(*tex);
+            if tex.ownership == TextureOwnership::Owned {
+                gl.delete_texture(tex.texture);
+            }
         }
+        gl.delete_vertex_array(self.vertex_array);
         gl.delete_buffer(self.vertex_buffer);
         gl.delete_buffer(self.element_array_buffer);
         for t in &self.textures_to_destroy {
@@ -331,7 +547,6 @@ impl Painter {
 
     /// This function must be called before Painter is dropped, as Painter has some OpenGL objects
     /// that should be deleted.
-
     pub fn destroy(&mut self, gl: &glow::Context) {
         if !self.destroyed {
             unsafe {
@@ -346,6 +561,146 @@ impl Painter {
     }
 }
 
+/// The OpenGL state that is changed while painting, apart from the state managed by
+/// [`SimBackend::set_graphics_state`].
+#[cfg_attr(test, derive(Debug, PartialEq))]
+struct GlState {
+    blend_src_rgb: u32,
+    blend_dst_rgb: u32,
+    blend_src_alpha: u32,
+    blend_dst_alpha: u32,
+    scissor_test: bool,
+    cull_face: bool,
+    framebuffer_srgb: bool,
+    program: Option<glow::Program>,
+    vertex_array: Option<glow::VertexArray>,
+    array_buffer: Option<glow::Buffer>,
+    element_array_buffer: Option<glow::Buffer>,
+    active_texture: u32,
+    /// The texture bound to `TEXTURE_2D` of unit 0, which is the one used for painting.
+    texture: Option<glow::Texture>,
+    scissor_box: [i32; 4],
+    viewport: [i32; 4],
+}
+
+impl GlState {
+    unsafe fn capture(gl: &glow::Context) -> Self {
+        let name = |parameter| NonZeroU32::new(gl.get_parameter_i32(parameter) as u32);
+
+        let active_texture = gl.get_parameter_i32(glow::ACTIVE_TEXTURE) as u32;
+        gl.active_texture(glow::TEXTURE0);
+        let texture = name(glow::TEXTURE_BINDING_2D).map(glow::NativeTexture);
+        gl.active_texture(active_texture);
+
+        let mut scissor_box = [0; 4];
+        gl.get_parameter_i32_slice(glow::SCISSOR_BOX, &mut scissor_box);
+        let mut viewport = [0; 4];
+        gl.get_parameter_i32_slice(glow::VIEWPORT, &mut viewport);
+
+        GlState {
+            blend_src_rgb: gl.get_parameter_i32(glow::BLEND_SRC_RGB) as u32,
+            blend_dst_rgb: gl.get_parameter_i32(glow::BLEND_DST_RGB) as u32,
+            blend_src_alpha: gl.get_parameter_i32(glow::BLEND_SRC_ALPHA) as u32,
+            blend_dst_alpha: gl.get_parameter_i32(glow::BLEND_DST_ALPHA) as u32,
+            scissor_test: gl.is_enabled(glow::SCISSOR_TEST),
+            cull_face: gl.is_enabled(glow::CULL_FACE),
+            framebuffer_srgb: gl.is_enabled(glow::FRAMEBUFFER_SRGB),
+            program: name(glow::CURRENT_PROGRAM).map(glow::NativeProgram),
+            vertex_array: name(glow::VERTEX_ARRAY_BINDING).map(glow::NativeVertexArray),
+            array_buffer: name(glow::ARRAY_BUFFER_BINDING).map(glow::NativeBuffer),
+            element_array_buffer: name(glow::ELEMENT_ARRAY_BUFFER_BINDING)
+                .map(glow::NativeBuffer),
+            active_texture,
+            texture,
+            scissor_box,
+            viewport,
+        }
+    }
+
+    unsafe fn restore(&self, gl: &glow::Context) {
+        gl.blend_func_separate(
+            self.blend_src_rgb,
+            self.blend_dst_rgb,
+            self.blend_src_alpha,
+            self.blend_dst_alpha,
+        );
+        set_enabled(gl, glow::SCISSOR_TEST, self.scissor_test);
+        set_enabled(gl, glow::CULL_FACE, self.cull_face);
+        set_enabled(gl, glow::FRAMEBUFFER_SRGB, self.framebuffer_srgb);
+        gl.use_program(self.program);
+        // The element array buffer binding belongs to the vertex array, so it is restored afterwards
+        gl.bind_vertex_array(self.vertex_array);
+        gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, self.element_array_buffer);
+        gl.bind_buffer(glow::ARRAY_BUFFER, self.array_buffer);
+        gl.active_texture(glow::TEXTURE0);
+        gl.bind_texture(glow::TEXTURE_2D, self.texture);
+        gl.active_texture(self.active_texture);
+        let [x, y, width, height] = self.scissor_box;
+        gl.scissor(x, y, width, height);
+        let [x, y, width, height] = self.viewport;
+        gl.viewport(x, y, width, height);
+    }
+}
+
+unsafe fn set_enabled(gl: &glow::Context, parameter: u32, enabled: bool) {
+    if enabled {
+        gl.enable(parameter);
+    } else {
+        gl.disable(parameter);
+    }
+}
+
+/// Scales a rect in X-Plane's boxels to the framebuffer pixels OpenGL works in.
+fn boxels_to_pixels(
+    rect: xplm::geometry::Rect<i32>,
+    pixels_per_boxel: f32,
+) -> xplm::geometry::Rect<i32> {
+    let scale = |boxels: i32| (boxels as f32 * pixels_per_boxel).round() as i32;
+    xplm::geometry::Rect::from_left_top_right_bottom(
+        scale(rect.left()),
+        scale(rect.top()),
+        scale(rect.right()),
+        scale(rect.bottom()),
+    )
+}
+
+/// Transforms an egui clip rect into a scissor box `[x, y, width, height]`.
+///
+/// egui clip rects are in points relative to the top left corner of the window, whereas the
+/// scissor box is in framebuffer pixels with the origin in the bottom left corner.
+/// The result is clamped to the window; `None` is returned if nothing of the mesh would be visible.
+fn scissor_box(
+    window: xplm::geometry::Rect<i32>,
+    pixels_per_point: f32,
+    clip_rect: egui::Rect,
+) -> Option<[i32; 4]> {
+    let width_in_pixels = window.right() - window.left();
+    let height_in_pixels = window.top() - window.bottom();
+
+    // Transform clip rect to physical pixels:
+    let clip_min_x = pixels_per_point * clip_rect.min.x;
+    let clip_min_y = pixels_per_point * clip_rect.min.y;
+    let clip_max_x = pixels_per_point * clip_rect.max.x;
+    let clip_max_y = pixels_per_point * clip_rect.max.y;
+
+    // Make sure clip rect can fit within the window:
+    let clip_min_x = clip_min_x.clamp(0.0, width_in_pixels as f32).round() as i32;
+    let clip_min_y = clip_min_y.clamp(0.0, height_in_pixels as f32).round() as i32;
+    let clip_max_x = clip_max_x.clamp(0.0, width_in_pixels as f32).round() as i32;
+    let clip_max_y = clip_max_y.clamp(0.0, height_in_pixels as f32).round() as i32;
+
+    if clip_max_x <= clip_min_x || clip_max_y <= clip_min_y {
+        return None;
+    }
+
+    Some([
+        window.left() + clip_min_x,
+        window.top() - clip_max_y,
+        clip_max_x - clip_min_x,
+        clip_max_y - clip_min_y,
+    ])
+}
+
 impl Drop for Painter {
     fn drop(&mut self) {
         if !self.destroyed {
@@ -355,3 +710,173 @@ impl Drop for Painter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use egui::{Pos2, Rect};
+    use xplm::geometry::Rect as WindowRect;
+
+    use super::{boxels_to_pixels, scissor_box};
+
+    fn clip(min: (f32, f32), max: (f32, f32)) -> Rect {
+        Rect::from_min_max(Pos2::new(min.0, min.1), Pos2::new(max.0, max.1))
+    }
+
+    #[test]
+    fn scissor_box_is_offset_by_the_window_position() {
+        let window = WindowRect::from_left_top_right_bottom(100, 500, 300, 300);
+        // 10 points from the left and top, 50x20 points large
+        let scissor = scissor_box(window, 1.0, clip((10.0, 10.0), (60.0, 30.0)));
+        assert_eq!(scissor, Some([110, 470, 50, 20]));
+    }
+
+    #[test]
+    fn scissor_box_scales_points_to_pixels() {
+        let window = WindowRect::from_left_top_right_bottom(100, 500, 300, 300);
+        let scissor = scissor_box(window, 2.0, clip((10.0, 10.0), (60.0, 30.0)));
+        assert_eq!(scissor, Some([120, 440, 100, 40]));
+
+        let scissor = scissor_box(window, 1.5, clip((1.0, 1.0), (3.0, 3.0)));
+        assert_eq!(scissor, Some([102, 495, 3, 3]));
+    }
+
+    #[test]
+    fn scissor_box_is_clamped_to_the_window() {
+        let window = WindowRect::from_left_top_right_bottom(100, 500, 300, 300);
+        let scissor = scissor_box(window, 1.0, clip((-50.0, -50.0), (1000.0, 1000.0)));
+        assert_eq!(scissor, Some([100, 300, 200, 200]));
+
+        let scissor = scissor_box(window, 1.0, clip((150.0, -10.0), (250.0, 20.0)));
+        assert_eq!(scissor, Some([250, 480, 50, 20]));
+    }
+
+    #[test]
+    fn boxels_are_scaled_to_framebuffer_pixels() {
+        let ltrb = |rect: WindowRect<i32>| (rect.left(), rect.top(), rect.right(), rect.bottom());
+        let window = WindowRect::from_left_top_right_bottom(100, 500, 300, 300);
+        assert_eq!(ltrb(boxels_to_pixels(window, 1.0)), (100, 500, 300, 300));
+        assert_eq!(ltrb(boxels_to_pixels(window, 2.0)), (200, 1000, 600, 600));
+        assert_eq!(ltrb(boxels_to_pixels(window, 1.25)), (125, 625, 375, 375));
+
+        // At a ui scale of 2, one point is one boxel and the scissor box covers twice as many pixels
+        let scissor = scissor_box(boxels_to_pixels(window, 2.0), 2.0, clip((10.0, 10.0), (60.0, 30.0)));
+        assert_eq!(scissor, Some([220, 940, 100, 40]));
+    }
+
+    #[test]
+    fn fully_clipped_meshes_have_no_scissor_box() {
+        let window = WindowRect::from_left_top_right_bottom(100, 500, 300, 300);
+        assert_eq!(scissor_box(window, 1.0, clip((210.0, 0.0), (300.0, 50.0))), None);
+        assert_eq!(scissor_box(window, 1.0, clip((0.0, -80.0), (50.0, -10.0))), None);
+        assert_eq!(scissor_box(window, 1.0, clip((20.0, 20.0), (20.0, 40.0))), None);
+        assert_eq!(scissor_box(window, 2.0, clip((100.0, 0.0), (150.0, 50.0))), None);
+    }
+
+    /// Paints over a state that differs in every captured aspect from what the painter sets up,
+    /// which must be back afterwards.
+    #[cfg(feature = "offscreen")]
+    #[test]
+    fn painting_restores_the_gl_state() {
+        use glow::HasContext;
+
+        use super::{GlState, Painter};
+        use crate::{mock::MockBackend, offscreen, ColorPipeline};
+
+        let context = offscreen::OffscreenContext::new(64, 64, true).unwrap();
+        let gl = context.gl();
+        let font_image = egui::FontImage {
+            version: 0,
+            width: 1,
+            height: 1,
+            pixels: vec![255],
+        };
+        let mut painter = Painter::new(gl, None, "", ColorPipeline::Linear).unwrap();
+        painter.upload_egui_texture(gl, &font_image).unwrap();
+        // Stands in for the objects of X-Plane or another plugin
+        let mut other = Painter::new(gl, None, "", ColorPipeline::GammaSpace).unwrap();
+        other.upload_egui_texture(gl, &font_image).unwrap();
+
+        // GlState only tracks unit 0, uploads must also leave the texture on the active unit alone
+        let capture = || unsafe {
+            let active_unit_texture = gl.get_parameter_i32(glow::TEXTURE_BINDING_2D);
+            (GlState::capture(gl), active_unit_texture)
+        };
+        let before = unsafe {
+            gl.use_program(Some(other.program));
+            gl.bind_vertex_array(Some(other.vertex_array));
+            gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(other.element_array_buffer));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(other.vertex_buffer));
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, other.egui_texture);
+            gl.active_texture(glow::TEXTURE3);
+            gl.bind_texture(glow::TEXTURE_2D, other.egui_texture);
+            gl.blend_func_separate(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA, glow::ONE, glow::ZERO);
+            gl.disable(glow::SCISSOR_TEST);
+            gl.enable(glow::CULL_FACE);
+            gl.disable(glow::FRAMEBUFFER_SRGB);
+            gl.scissor(1, 2, 3, 4);
+            gl.viewport(5, 6, 7, 8);
+            capture()
+        };
+        for (version, (_, meshes)) in offscreen::canned_scenes().into_iter().enumerate() {
+            // Like XplmGuiContext::draw, which uploads a changed font before painting
+            let font_image = egui::FontImage {
+                version: version as u64 + 1,
+                ..font_image.clone()
+            };
+            painter.upload_egui_texture(gl, &font_image).unwrap();
+            assert_eq!(capture(), before);
+            painter.paint_meshes(gl, &MockBackend::default(), context.window(), 1.0, 1.0, &meshes);
+            assert_eq!(capture(), before);
+        }
+
+        // Textures are also uploaded outside of draw, e.g. by plugin code between frames
+        let pixels = [egui::Color32::RED; 4];
+        let texture = painter.alloc_user_texture(gl, [2, 2], &pixels).unwrap();
+        assert_eq!(capture(), before);
+        painter
+            .update_user_texture(gl, texture, [1, 1], [1, 1], &pixels[..1])
+            .unwrap();
+        assert_eq!(capture(), before);
+
+        painter.destroy(gl);
+        other.destroy(gl);
+    }
+
+    #[cfg(feature = "offscreen")]
+    #[test]
+    fn invalid_texture_uploads_are_errors() {
+        use super::{Painter, TextureOwnership};
+        use crate::{offscreen, ColorPipeline, Error};
+
+        let context = offscreen::OffscreenContext::new(64, 64, true).unwrap();
+        let gl = context.gl();
+        let mut painter = Painter::new(gl, None, "", ColorPipeline::Linear).unwrap();
+        let pixels = [egui::Color32::RED; 4];
+
+        let alloc = painter.alloc_user_texture(gl, [0, 4], &[]);
+        assert!(matches!(alloc, Err(Error::TextureSize([0, 4]))), "{alloc:?}");
+        let alloc = painter.alloc_user_texture(gl, [2, 3], &pixels);
+        assert!(matches!(alloc, Err(Error::TextureDataLength { len: 16, .. })), "{alloc:?}");
+
+        let texture = painter.alloc_user_texture(gl, [2, 2], &pixels).unwrap();
+        let update = painter.update_user_texture(gl, texture, [1, 0], [2, 1], &pixels[..2]);
+        assert!(matches!(update, Err(Error::TextureRegion { texture_size: [2, 2], .. })), "{update:?}");
+        let update = painter.update_user_texture(gl, texture, [usize::MAX, 0], [1, 1], &pixels[..1]);
+        assert!(matches!(update, Err(Error::TextureRegion { .. })), "{update:?}");
+        let update = painter.update_user_texture(gl, texture, [0, 0], [2, 2], &pixels[..3]);
+        assert!(matches!(update, Err(Error::TextureDataLength { .. })), "{update:?}");
+        let update = painter.update_user_texture(gl, egui::TextureId::Egui, [0, 0], [1, 1], &pixels[..1]);
+        assert!(matches!(update, Err(Error::UnknownTexture(egui::TextureId::Egui))), "{update:?}");
+
+        // The size of a registered texture is unknown, so only the pixel data can be checked
+        let egui::TextureId::User(id) = texture else { unreachable!() };
+        let native = painter.user_textures[&id].texture;
+        let borrowed = painter.register_native_texture(native, TextureOwnership::Borrowed);
+        painter
+            .update_user_texture(gl, borrowed, [0, 0], [1, 1], &pixels[..1])
+            .unwrap();
+
+        painter.destroy(gl);
+    }
+}
@@ -0,0 +1,243 @@
+#![allow(unsafe_code)]
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use glow::HasContext;
+
+/// How many frames of history are kept.
+const HISTORY_LEN: usize = 240;
+
+/// The parts of a frame that are measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Collecting the input gathered from the XPLM callbacks.
+    Input,
+    /// Running the ui in `egui::CtxRef::run`.
+    Run,
+    /// Turning shapes into meshes.
+    Tessellate,
+    /// Uploading the font texture.
+    TextureUpload,
+    /// Issuing the draw calls.
+    Paint,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Input,
+        Phase::Run,
+        Phase::Tessellate,
+        Phase::TextureUpload,
+        Phase::Paint,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Input => "input",
+            Phase::Run => "run",
+            Phase::Tessellate => "tessellate",
+            Phase::TextureUpload => "texture upload",
+            Phase::Paint => "paint",
+        }
+    }
+
+    fn color(self) -> egui::Color32 {
+        match self {
+            Phase::Input => egui::Color32::from_rgb(0x4c, 0x72, 0xb0),
+            Phase::Run => egui::Color32::from_rgb(0xdd, 0x84, 0x52),
+            Phase::Tessellate => egui::Color32::from_rgb(0x55, 0xa8, 0x68),
+            Phase::TextureUpload => egui::Color32::from_rgb(0xc4, 0x4e, 0x52),
+            Phase::Paint => egui::Color32::from_rgb(0x81, 0x72, 0xb3),
+        }
+    }
+}
+
+/// The measurements of a single frame.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameProfile {
+    /// CPU time spent in each [`Phase`], in the order of [`Phase::ALL`].
+    pub cpu: [Duration; 5],
+    /// GPU time spent painting, if timer queries are available.
+    /// This arrives a few frames late.
+    pub gpu_paint: Option<Duration>,
+}
+
+impl FrameProfile {
+    pub fn cpu_time(&self, phase: Phase) -> Duration {
+        self.cpu[phase as usize]
+    }
+
+    pub fn cpu_total(&self) -> Duration {
+        self.cpu.iter().sum()
+    }
+}
+
+/// Measures how much of the sim's frame budget goes into egui.
+///
+/// Enable it with [`crate::XplmGuiContext::set_profiling`].
+pub struct Profiler {
+    history: VecDeque<FrameProfile>,
+    current: FrameProfile,
+    /// Index of the frame that is currently measured.
+    frame_index: u64,
+    timer_queries: bool,
+    /// Timer queries that are waiting for their result, with the index of their frame.
+    pending_queries: VecDeque<(u64, glow::Query)>,
+    show_overlay: bool,
+}
+
+impl Profiler {
+    pub(crate) fn new(gl: &glow::Context) -> Self {
+        let timer_queries = gl.supported_extensions().contains("GL_ARB_timer_query")
+            || gl.supported_extensions().contains("GL_EXT_timer_query");
+        Self {
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            current: Default::default(),
+            frame_index: 0,
+            timer_queries,
+            pending_queries: VecDeque::new(),
+            show_overlay: false,
+        }
+    }
+
+    /// Adds the time passed since `start` to `phase` of the current frame.
+    pub(crate) fn record(&mut self, phase: Phase, start: Instant) {
+        self.current.cpu[phase as usize] += start.elapsed();
+    }
+
+    pub(crate) fn begin_gpu_paint(&mut self, gl: &glow::Context) {
+        if !self.timer_queries {
+            return;
+        }
+        unsafe {
+            match gl.create_query() {
+                Ok(query) => {
+                    gl.begin_query(glow::TIME_ELAPSED, query);
+                    self.pending_queries.push_back((self.frame_index, query));
+                }
+                Err(err) => {
+                    log!("egui: disabling gpu profiling, failed to create query: {err}");
+                    self.timer_queries = false;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn end_gpu_paint(&mut self, gl: &glow::Context) {
+        if self.timer_queries {
+            unsafe { gl.end_query(glow::TIME_ELAPSED) };
+        }
+    }
+
+    /// Finishes the current frame and collects the results of finished timer queries.
+    pub(crate) fn end_frame(&mut self, gl: &glow::Context) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(self.current);
+        self.current = Default::default();
+
+        while let Some(&(frame_index, query)) = self.pending_queries.front() {
+            let available =
+                unsafe { gl.get_query_parameter_u32(query, glow::QUERY_RESULT_AVAILABLE) };
+            if available == 0 {
+                break;
+            }
+            self.pending_queries.pop_front();
+            let nanos = unsafe {
+                let nanos = gl.get_query_parameter_u32(query, glow::QUERY_RESULT);
+                gl.delete_query(query);
+                nanos
+            };
+            let age = (self.frame_index - frame_index) as usize;
+            if let Some(index) = self.history.len().checked_sub(age + 1) {
+                self.history[index].gpu_paint = Some(Duration::from_nanos(nanos as u64));
+            }
+        }
+        self.frame_index += 1;
+    }
+
+    pub(crate) fn destroy(&mut self, gl: &glow::Context) {
+        for (_, query) in self.pending_queries.drain(..) {
+            unsafe { gl.delete_query(query) };
+        }
+    }
+
+    /// The measured frames, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &FrameProfile> {
+        self.history.iter()
+    }
+
+    /// Whether the profiler window is shown on top of the ui.
+    pub fn set_show_overlay(&mut self, show: bool) {
+        self.show_overlay = show;
+    }
+
+    pub(crate) fn show_overlay(&self, ctx: &egui::CtxRef) {
+        if self.show_overlay {
+            egui::Window::new("egui frame times").show(ctx, |ui| self.ui(ui));
+            // The numbers change every frame, also with repaint on demand
+            ctx.request_repaint();
+        }
+    }
+
+    /// Shows averages and a graph of the frame history.
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        let frames = self.history.len().max(1) as u32;
+        egui::Grid::new("xplm_egui_profiler").show(ui, |ui| {
+            for phase in Phase::ALL {
+                let total: Duration = self.history.iter().map(|f| f.cpu_time(phase)).sum();
+                ui.colored_label(phase.color(), phase.name());
+                ui.label(format!("{:.3} ms", (total / frames).as_secs_f64() * 1e3));
+                ui.end_row();
+            }
+            let gpu: Vec<Duration> = self.history.iter().filter_map(|f| f.gpu_paint).collect();
+            ui.label("gpu paint");
+            if gpu.is_empty() {
+                ui.label("n/a");
+            } else {
+                let total: Duration = gpu.iter().sum();
+                let avg = total / gpu.len() as u32;
+                ui.label(format!("{:.3} ms", avg.as_secs_f64() * 1e3));
+            }
+            ui.end_row();
+        });
+
+        let (rect, _) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width().max(HISTORY_LEN as f32), 80.0),
+            egui::Sense::hover(),
+        );
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        let max_total = self
+            .history
+            .iter()
+            .map(FrameProfile::cpu_total)
+            .max()
+            .unwrap_or_default()
+            .max(Duration::from_micros(100));
+        let bar_width = rect.width() / HISTORY_LEN as f32;
+        let points_per_sec = rect.height() / max_total.as_secs_f32();
+        for (i, frame) in self.history.iter().enumerate() {
+            let x = rect.left() + i as f32 * bar_width;
+            let mut y = rect.bottom();
+            for phase in Phase::ALL {
+                let height = frame.cpu_time(phase).as_secs_f32() * points_per_sec;
+                let bar = egui::Rect::from_min_max(
+                    egui::pos2(x, y - height),
+                    egui::pos2(x + bar_width, y),
+                );
+                painter.rect_filled(bar, 0.0, phase.color());
+                y -= height;
+            }
+        }
+        ui.label(format!(
+            "max {:.3} ms cpu",
+            max_total.as_secs_f64() * 1e3
+        ));
+    }
+}
@@ -0,0 +1,142 @@
+//! Recording of the input egui got, to reproduce bugs by replaying it with
+//! [`crate::XplmGuiContext::replay_frame`].
+//!
+//! A recording is a text file with one JSON object per frame, see [`RecordedFrame`].
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// An XPLM event as it reached [`crate::XplmGuiContext::input_state`], before it was translated for egui.
+///
+/// Positions are in X-Plane's global boxel coordinates. The XPLM enums are kept as their `Debug` names,
+/// they are only meant to be read by humans.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RawEvent {
+    Key {
+        key: String,
+        char: Option<char>,
+        modifiers: egui::Modifiers,
+        pressed: bool,
+    },
+    Mouse {
+        x: i32,
+        y: i32,
+        action: String,
+        button: egui::PointerButton,
+    },
+    Scroll {
+        x: i32,
+        y: i32,
+        scroll_x: i32,
+        scroll_y: i32,
+    },
+    /// Only recorded when the cursor actually moved.
+    Cursor { x: i32, y: i32 },
+}
+
+/// The input of one frame.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// The XPLM events since the last frame, in the order they arrived.
+    pub raw_events: Vec<RawEvent>,
+    /// What egui got from them, which is what is replayed.
+    pub input: egui::RawInput,
+}
+
+/// Writes the frames to a file, see `XplmInputState::start_recording`.
+pub(crate) struct InputRecorder {
+    writer: BufWriter<File>,
+    raw_events: Vec<RawEvent>,
+}
+
+impl InputRecorder {
+    pub(crate) fn create(path: &Path) -> Result<Self, Error> {
+        let file = File::create(path).map_err(|err| Error::Recording(err.to_string()))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            raw_events: Vec::new(),
+        })
+    }
+
+    pub(crate) fn record_event(&mut self, event: RawEvent) {
+        self.raw_events.push(event);
+    }
+
+    /// Writes the events since the last frame together with `input`.
+    ///
+    /// Returns `false` if writing failed, in which case the recording should be stopped.
+    pub(crate) fn record_frame(&mut self, input: &egui::RawInput) -> bool {
+        let frame = RecordedFrame {
+            raw_events: std::mem::take(&mut self.raw_events),
+            input: input.clone(),
+        };
+        let result = serde_json::to_writer(&mut self.writer, &frame)
+            .map_err(|err| err.to_string())
+            .and_then(|()| writeln!(self.writer).map_err(|err| err.to_string()));
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                log!("egui: stopped input recording: {err}");
+                false
+            }
+        }
+    }
+
+    pub(crate) fn finish(mut self) {
+        if let Err(err) = self.writer.flush() {
+            log!("egui: failed to finish input recording: {err}");
+        }
+    }
+}
+
+/// A recording that is played back frame by frame.
+#[derive(Debug, Clone)]
+pub struct InputReplay {
+    frames: std::vec::IntoIter<RecordedFrame>,
+}
+
+impl InputReplay {
+    /// Reads a recording made with `XplmInputState::start_recording`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::open(path).map_err(|err| Error::Recording(err.to_string()))?;
+        let mut frames = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|err| Error::Recording(err.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let frame = serde_json::from_str(&line)
+                .map_err(|err| Error::Recording(format!("line {}: {err}", index + 1)))?;
+            frames.push(frame);
+        }
+        Ok(Self::from_frames(frames))
+    }
+
+    /// Replays frames that were e.g. built by hand in a test.
+    pub fn from_frames(frames: Vec<RecordedFrame>) -> Self {
+        Self {
+            frames: frames.into_iter(),
+        }
+    }
+
+    /// How many frames are left.
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The frames that are left, e.g. to look at the XPLM events of a bug report.
+    pub fn frames(&self) -> &[RecordedFrame] {
+        self.frames.as_slice()
+    }
+
+    pub(crate) fn next_frame(&mut self) -> Option<RecordedFrame> {
+        self.frames.next()
+    }
+}
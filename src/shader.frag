@@ -1,13 +1,20 @@
-#version 140
+#ifdef NEW_SHADER_INTERFACE
+#define I in
+#define texture2D texture
+out vec4 f_color;
+#else
+#define I varying
+#define f_color gl_FragColor
+#endif
+
 uniform sampler2D u_sampler;
 
-in vec4 v_rgba;
-in vec2 v_tc;
-out vec4 f_color;
+I vec4 v_rgba;
+I vec2 v_tc;
 
 void main() {
-    // The texture sampler is sRGB aware, and OpenGL already expects linear rgba output
-    // so no need for any sRGB conversions here:
+    // Textures are stored as sRGB in the linear pipeline, so the sampler decodes them, and as plain RGBA
+    // in the gamma space pipeline. Either way, they are in the same space as v_rgba:
     //gl_FragColor = v_rgba * texture2D(u_sampler, v_tc);
     //f_color = vec4(0.2f, 1.0f, 0.5f, 1.0f);
     f_color = v_rgba * texture2D(u_sampler, v_tc);
@@ -1,7 +1,12 @@
-#version 140
+#ifdef NEW_SHADER_INTERFACE
 #define I in
 #define O out
 #define V(x) x
+#else
+#define I attribute
+#define O varying
+#define V(x) vec3(x)
+#endif
 
 uniform vec2 u_screen_size;
 I vec2 a_pos;
@@ -19,8 +24,12 @@ vec3 linear_from_srgb(vec3 srgb) {
 }
 
 vec4 linear_from_srgba(vec4 srgba) {
-    //return vec4(linear_from_srgb(srgba.rgb), srgba.a / 255.0);
+#ifdef LINEAR_COLOR_PIPELINE
+    return vec4(linear_from_srgb(srgba.rgb), srgba.a / 255.0);
+#else
+    // The framebuffer is in gamma space, so the colors are passed on as they are
     return srgba / 255.0;
+#endif
 }
 
 void main() {
@@ -29,7 +38,7 @@ void main() {
                       1.0 - 2.0 * a_pos.y / u_screen_size.y,
                       0.0,
                       1.0);
-    // egui encodes vertex colors in gamma space, so we must decode the colors here:
+    // egui encodes vertex colors in gamma space, so we must decode the colors here if blending in linear space:
     v_rgba = linear_from_srgba(a_srgba);
     v_tc = a_tc;
 }
@@ -0,0 +1,88 @@
+#![allow(unsafe_code)]
+
+use glow::HasContext;
+
+/// The GLSL dialects the egui shaders can be compiled as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderVersion {
+    /// GLSL 1.20 with `attribute`/`varying`, for old compatibility contexts.
+    Gl120,
+    /// GLSL 1.40 with `in`/`out`.
+    Gl140,
+    /// GLSL 3.30 core, for core profile contexts that reject the deprecated built-ins.
+    Gl330,
+}
+
+impl ShaderVersion {
+    /// Picks the newest dialect supported by the current context.
+    pub fn get(gl: &glow::Context) -> Self {
+        let glsl_version = unsafe { gl.get_parameter_string(glow::SHADING_LANGUAGE_VERSION) };
+        Self::parse(&glsl_version)
+    }
+
+    /// Parses a `GL_SHADING_LANGUAGE_VERSION` string like `"4.60 NVIDIA"`.
+    pub(crate) fn parse(glsl_version: &str) -> Self {
+        let number = glsl_version
+            .split_whitespace()
+            .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+            .unwrap_or("");
+        let mut parts = number.split('.');
+        let major: u32 = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+        // The minor version has two digits ("1.40"), but some drivers leave out the trailing zero ("3.3")
+        let minor: u32 = parts
+            .next()
+            .and_then(|s| {
+                let digits = s.get(..2).unwrap_or(s);
+                let minor: u32 = digits.parse().ok()?;
+                Some(if digits.len() == 1 { minor * 10 } else { minor })
+            })
+            .unwrap_or(0);
+        if (major, minor) < (1, 40) {
+            ShaderVersion::Gl120
+        } else if (major, minor) < (3, 30) {
+            ShaderVersion::Gl140
+        } else {
+            ShaderVersion::Gl330
+        }
+    }
+
+    /// The `#version` line and the defines that select the shader interface.
+    pub fn version_declaration(&self) -> &'static str {
+        match self {
+            ShaderVersion::Gl120 => "#version 120\n",
+            ShaderVersion::Gl140 => "#version 140\n#define NEW_SHADER_INTERFACE\n",
+            ShaderVersion::Gl330 => "#version 330 core\n#define NEW_SHADER_INTERFACE\n",
+        }
+    }
+
+    /// Prepends the version declaration and `shader_prefix` to `source`.
+    pub(crate) fn preprocess(&self, shader_prefix: &str, source: &str) -> String {
+        format!("{}{}\n{}", self.version_declaration(), shader_prefix, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShaderVersion;
+
+    #[test]
+    fn parses_driver_version_strings() {
+        assert_eq!(ShaderVersion::parse("4.60 NVIDIA"), ShaderVersion::Gl330);
+        assert_eq!(ShaderVersion::parse("1.20"), ShaderVersion::Gl120);
+        assert_eq!(ShaderVersion::parse("3.3"), ShaderVersion::Gl330);
+        assert_eq!(ShaderVersion::parse("1.4"), ShaderVersion::Gl140);
+        assert_eq!(ShaderVersion::parse("OpenGL ES GLSL ES 1.00"), ShaderVersion::Gl120);
+        assert_eq!(ShaderVersion::parse(""), ShaderVersion::Gl120);
+    }
+
+    #[test]
+    fn version_is_declared_on_the_first_line() {
+        for version in [ShaderVersion::Gl120, ShaderVersion::Gl140, ShaderVersion::Gl330] {
+            let source = version.preprocess("#define FOO\n", "void main() {}\n");
+            let first_line = source.lines().next().unwrap();
+            assert!(first_line.starts_with("#version "), "{version:?}: {source}");
+            assert!(source.contains("#define FOO"), "{version:?}: {source}");
+            assert!(source.ends_with("void main() {}\n"), "{version:?}: {source}");
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use xplm::{geometry::Rect, window::WindowOptions};
+
+use crate::{input::XplmInputState, Error, GuiDelegate, XplmGuiContext, XplmWindow, XplmWindowRef};
+
+/// An egui application shown in an X-Plane window by [`EguiWindow`].
+pub trait App {
+    /// Shows the ui. Called once per frame while the window is visible.
+    fn ui(&mut self, ctx: &egui::CtxRef);
+}
+
+/// A [`GuiDelegate`] that shows an [`App`], forwarding all window events to egui.
+pub struct EguiWindow<A: App> {
+    gui: XplmGuiContext,
+    app: A,
+}
+
+impl<A: App> EguiWindow<A> {
+    pub fn new(app: A) -> Result<Self, Error> {
+        Ok(Self::with_context(XplmGuiContext::new()?, app))
+    }
+
+    /// Like [`EguiWindow::new`], but with a context that was e.g. created with [`XplmGuiContext::with_options`].
+    pub fn with_context(gui: XplmGuiContext, app: A) -> Self {
+        Self { gui, app }
+    }
+
+    pub fn app(&self) -> &A {
+        &self.app
+    }
+
+    pub fn app_mut(&mut self) -> &mut A {
+        &mut self.app
+    }
+
+    /// The context, to change e.g. the scale or the clock.
+    pub fn gui(&self) -> &XplmGuiContext {
+        &self.gui
+    }
+
+    pub fn gui_mut(&mut self) -> &mut XplmGuiContext {
+        &mut self.gui
+    }
+}
+
+impl<A: App + 'static> EguiWindow<A> {
+    /// Creates an X-Plane window showing `app`.
+    pub fn create(
+        geometry: Rect<i32>,
+        options: WindowOptions,
+        app: A,
+    ) -> Result<XplmWindowRef, Error> {
+        Ok(XplmWindow::create(geometry, Self::new(app)?, options))
+    }
+}
+
+impl<A: App + 'static> GuiDelegate for EguiWindow<A> {
+    fn draw(&mut self, window: &XplmWindow) {
+        let app = &mut self.app;
+        self.gui.update(window, |ctx| app.ui(ctx));
+        self.gui.draw(window);
+    }
+
+    fn input_state(&mut self) -> &mut XplmInputState {
+        &mut self.gui.input_state
+    }
+}
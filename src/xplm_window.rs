@@ -0,0 +1,443 @@
+//! X-Plane windows that forward all their events to an [`XplmInputState`], right clicks included.
+//!
+//! [`xplm::window::Window`] registers no `handleRightClickFunc`, so X-Plane never reports right clicks
+//! to its [`xplm::window::WindowDelegate`]. [`XplmWindow`] is created with `XPLMCreateWindowEx` directly
+//! and registers all handlers.
+
+#![allow(unsafe_code)]
+
+use std::{
+    ffi::{c_char, c_int, c_void},
+    mem,
+    ops::Deref,
+    ptr,
+};
+
+use egui::Modifiers;
+use xplm::{
+    geometry::{Point, Rect},
+    window::{Cursor, Key, MouseAction, WindowOptions},
+};
+
+use crate::input::{xplm_modifiers, XplmInputState};
+
+/// The behaviour of an [`XplmWindow`].
+///
+/// Implemented by [`crate::EguiWindow`] and [`crate::EpiWindow`].
+pub trait GuiDelegate: 'static {
+    /// Draws the window, once per frame while it is visible.
+    fn draw(&mut self, window: &XplmWindow);
+    /// The input state that gets the keyboard, mouse, scroll and cursor events of the window.
+    fn input_state(&mut self) -> &mut XplmInputState;
+}
+
+/// An X-Plane window whose events are handled by a [`GuiDelegate`].
+pub struct XplmWindow {
+    id: xplm_sys::XPLMWindowID,
+}
+
+/// Owns an [`XplmWindow`] and its delegate. The window is destroyed when this is dropped.
+pub struct XplmWindowRef {
+    registered: Box<Registered>,
+}
+
+/// What the callbacks get as their refcon. The window and the delegate are separate fields, so the
+/// delegate can be borrowed mutably while it gets the window.
+struct Registered {
+    window: XplmWindow,
+    delegate: Box<dyn GuiDelegate>,
+}
+
+impl XplmWindow {
+    /// Creates a window with the provided geometry in boxels and returns a reference to it.
+    ///
+    /// Like with [`xplm::window::Window::new`], the window is not visible at first.
+    pub fn create<D: GuiDelegate>(
+        geometry: Rect<i32>,
+        delegate: D,
+        _options: WindowOptions,
+    ) -> XplmWindowRef {
+        let mut registered = Box::new(Registered {
+            window: XplmWindow { id: ptr::null_mut() },
+            delegate: Box::new(delegate),
+        });
+        let registered_ptr: *mut Registered = &mut *registered;
+
+        let mut window_info = xplm_sys::XPLMCreateWindow_t {
+            structSize: mem::size_of::<xplm_sys::XPLMCreateWindow_t>() as _,
+            left: geometry.left(),
+            top: geometry.top(),
+            right: geometry.right(),
+            bottom: geometry.bottom(),
+            visible: 0,
+            drawWindowFunc: Some(window_draw),
+            handleMouseClickFunc: Some(window_mouse),
+            handleKeyFunc: Some(window_key),
+            handleCursorFunc: Some(window_cursor),
+            handleMouseWheelFunc: Some(window_scroll),
+            refcon: registered_ptr.cast(),
+            decorateAsFloatingWindow: 0,
+            layer: xplm_sys::xplm_WindowLayerFloatingWindows as _,
+            handleRightClickFunc: Some(window_right_click),
+        };
+        registered.window.id = unsafe { xplm_sys::XPLMCreateWindowEx(&mut window_info) };
+
+        XplmWindowRef { registered }
+    }
+
+    /// The window geometry in X-Plane's global boxel coordinates.
+    pub fn geometry(&self) -> Rect<i32> {
+        let (mut left, mut top, mut right, mut bottom) = (0, 0, 0, 0);
+        unsafe {
+            xplm_sys::XPLMGetWindowGeometry(self.id, &mut left, &mut top, &mut right, &mut bottom);
+        }
+        Rect::from_left_top_right_bottom(left, top, right, bottom)
+    }
+
+    pub fn set_geometry(&self, geometry: Rect<i32>) {
+        unsafe {
+            xplm_sys::XPLMSetWindowGeometry(
+                self.id,
+                geometry.left(),
+                geometry.top(),
+                geometry.right(),
+                geometry.bottom(),
+            );
+        }
+    }
+
+    pub fn visible(&self) -> bool {
+        unsafe { xplm_sys::XPLMGetWindowIsVisible(self.id) == 1 }
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        unsafe { xplm_sys::XPLMSetWindowIsVisible(self.id, visible as _) }
+    }
+
+    pub fn take_keyboard_focus(&self) {
+        unsafe { xplm_sys::XPLMTakeKeyboardFocus(self.id) }
+    }
+
+    /// Gives the keyboard focus back to X-Plane.
+    pub fn release_keyboard_focus(&self) {
+        unsafe { xplm_sys::XPLMTakeKeyboardFocus(ptr::null_mut()) }
+    }
+}
+
+impl Deref for XplmWindowRef {
+    type Target = XplmWindow;
+
+    fn deref(&self) -> &XplmWindow {
+        &self.registered.window
+    }
+}
+
+impl Drop for XplmWindowRef {
+    fn drop(&mut self) {
+        unsafe { xplm_sys::XPLMDestroyWindow(self.registered.window.id) }
+    }
+}
+
+/// # Safety
+/// `refcon` must be the pointer registered in [`XplmWindow::create`], whose [`XplmWindowRef`] is still alive.
+unsafe fn registered<'a>(refcon: *mut c_void) -> &'a mut Registered {
+    &mut *refcon.cast::<Registered>()
+}
+
+unsafe extern "C" fn window_draw(_window: xplm_sys::XPLMWindowID, refcon: *mut c_void) {
+    let Registered { window, delegate } = registered(refcon);
+    delegate.draw(window);
+}
+
+unsafe extern "C" fn window_key(
+    _window: xplm_sys::XPLMWindowID,
+    key: c_char,
+    flags: xplm_sys::XPLMKeyFlags,
+    virtual_key: c_char,
+    refcon: *mut c_void,
+    losing_focus: c_int,
+) {
+    if losing_focus != 0 {
+        return;
+    }
+    if let Some((key, char, modifiers, pressed)) = key_event(key, flags, virtual_key) {
+        registered(refcon)
+            .delegate
+            .input_state()
+            .key_input(key, char, modifiers, pressed);
+    }
+}
+
+unsafe extern "C" fn window_mouse(
+    _window: xplm_sys::XPLMWindowID,
+    x: c_int,
+    y: c_int,
+    status: xplm_sys::XPLMMouseStatus,
+    refcon: *mut c_void,
+) -> c_int {
+    mouse_button(refcon, x, y, status, egui::PointerButton::Primary)
+}
+
+unsafe extern "C" fn window_right_click(
+    _window: xplm_sys::XPLMWindowID,
+    x: c_int,
+    y: c_int,
+    status: xplm_sys::XPLMMouseStatus,
+    refcon: *mut c_void,
+) -> c_int {
+    mouse_button(refcon, x, y, status, egui::PointerButton::Secondary)
+}
+
+/// Returns 1 if egui consumes the click, 0 to let X-Plane pass it on.
+unsafe fn mouse_button(
+    refcon: *mut c_void,
+    x: c_int,
+    y: c_int,
+    status: xplm_sys::XPLMMouseStatus,
+    button: egui::PointerButton,
+) -> c_int {
+    let Some(action) = mouse_action(status) else {
+        return 0;
+    };
+    let Registered { window, delegate } = registered(refcon);
+    let consumed = delegate
+        .input_state()
+        .mouse_input(window, Point::from((x, y)), action, button);
+    consumed as c_int
+}
+
+unsafe extern "C" fn window_cursor(
+    _window: xplm_sys::XPLMWindowID,
+    x: c_int,
+    y: c_int,
+    refcon: *mut c_void,
+) -> xplm_sys::XPLMCursorStatus {
+    let Registered { window, delegate } = registered(refcon);
+    let cursor = delegate.input_state().cursor(window, Point::from((x, y)));
+    let status = match cursor {
+        Cursor::Default => xplm_sys::xplm_CursorDefault,
+        Cursor::Arrow => xplm_sys::xplm_CursorArrow,
+        Cursor::None => xplm_sys::xplm_CursorHidden,
+    };
+    status as xplm_sys::XPLMCursorStatus
+}
+
+unsafe extern "C" fn window_scroll(
+    _window: xplm_sys::XPLMWindowID,
+    x: c_int,
+    y: c_int,
+    wheel: c_int,
+    clicks: c_int,
+    refcon: *mut c_void,
+) -> c_int {
+    // Wheel 1 is the horizontal one
+    let (scroll_x, scroll_y) = if wheel == 1 { (clicks, 0) } else { (0, clicks) };
+    let Registered { window, delegate } = registered(refcon);
+    let consumed = delegate
+        .input_state()
+        .scroll_input(window, Point::from((x, y)), scroll_x, scroll_y);
+    consumed as c_int
+}
+
+fn mouse_action(status: xplm_sys::XPLMMouseStatus) -> Option<MouseAction> {
+    match status as u32 {
+        xplm_sys::xplm_MouseDown => Some(MouseAction::Down),
+        xplm_sys::xplm_MouseDrag => Some(MouseAction::Drag),
+        xplm_sys::xplm_MouseUp => Some(MouseAction::Up),
+        _ => None,
+    }
+}
+
+/// Splits the arguments of the key callback into what [`XplmInputState::key_input`] takes.
+///
+/// Returns `None` for flags without a press or release and for unknown keys, which
+/// [`xplm::window::KeyEvent`] can't represent either.
+fn key_event(
+    key: c_char,
+    flags: xplm_sys::XPLMKeyFlags,
+    virtual_key: c_char,
+) -> Option<(Key, Option<char>, Modifiers, bool)> {
+    let flag = |flag: u32| flags & flag as xplm_sys::XPLMKeyFlags != 0;
+    let pressed = if flag(xplm_sys::xplm_DownFlag) {
+        true
+    } else if flag(xplm_sys::xplm_UpFlag) {
+        false
+    } else {
+        return None;
+    };
+    // Printable characters, including spaces and tabs
+    let char = match key as u8 {
+        c @ (b'\t' | b' '..=b'~') => Some(c as char),
+        _ => None,
+    };
+    let modifiers = xplm_modifiers(
+        flag(xplm_sys::xplm_ControlFlag),
+        flag(xplm_sys::xplm_OptionAltFlag),
+        flag(xplm_sys::xplm_ShiftFlag),
+    );
+    Some((key_from_xplm(virtual_key)?, char, modifiers, pressed))
+}
+
+/// Converts an XPLM virtual key code into a [`Key`], like [`xplm::window::KeyEvent`] does.
+fn key_from_xplm(virtual_key: c_char) -> Option<Key> {
+    use xplm_sys as sys;
+    let key = match virtual_key as u8 as u32 {
+        sys::XPLM_VK_BACK => Key::Back,
+        sys::XPLM_VK_TAB => Key::Tab,
+        sys::XPLM_VK_CLEAR => Key::Clear,
+        sys::XPLM_VK_RETURN => Key::Return,
+        sys::XPLM_VK_ESCAPE => Key::Escape,
+        sys::XPLM_VK_SPACE => Key::Space,
+        sys::XPLM_VK_PRIOR => Key::Prior,
+        sys::XPLM_VK_NEXT => Key::Next,
+        sys::XPLM_VK_END => Key::End,
+        sys::XPLM_VK_HOME => Key::Home,
+        sys::XPLM_VK_LEFT => Key::Left,
+        sys::XPLM_VK_UP => Key::Up,
+        sys::XPLM_VK_RIGHT => Key::Right,
+        sys::XPLM_VK_DOWN => Key::Down,
+        sys::XPLM_VK_SELECT => Key::Select,
+        sys::XPLM_VK_PRINT => Key::Print,
+        sys::XPLM_VK_EXECUTE => Key::Execute,
+        sys::XPLM_VK_SNAPSHOT => Key::Snapshot,
+        sys::XPLM_VK_INSERT => Key::Insert,
+        sys::XPLM_VK_DELETE => Key::Delete,
+        sys::XPLM_VK_HELP => Key::Help,
+        sys::XPLM_VK_0 => Key::Key0,
+        sys::XPLM_VK_1 => Key::Key1,
+        sys::XPLM_VK_2 => Key::Key2,
+        sys::XPLM_VK_3 => Key::Key3,
+        sys::XPLM_VK_4 => Key::Key4,
+        sys::XPLM_VK_5 => Key::Key5,
+        sys::XPLM_VK_6 => Key::Key6,
+        sys::XPLM_VK_7 => Key::Key7,
+        sys::XPLM_VK_8 => Key::Key8,
+        sys::XPLM_VK_9 => Key::Key9,
+        sys::XPLM_VK_A => Key::A,
+        sys::XPLM_VK_B => Key::B,
+        sys::XPLM_VK_C => Key::C,
+        sys::XPLM_VK_D => Key::D,
+        sys::XPLM_VK_E => Key::E,
+        sys::XPLM_VK_F => Key::F,
+        sys::XPLM_VK_G => Key::G,
+        sys::XPLM_VK_H => Key::H,
+        sys::XPLM_VK_I => Key::I,
+        sys::XPLM_VK_J => Key::J,
+        sys::XPLM_VK_K => Key::K,
+        sys::XPLM_VK_L => Key::L,
+        sys::XPLM_VK_M => Key::M,
+        sys::XPLM_VK_N => Key::N,
+        sys::XPLM_VK_O => Key::O,
+        sys::XPLM_VK_P => Key::P,
+        sys::XPLM_VK_Q => Key::Q,
+        sys::XPLM_VK_R => Key::R,
+        sys::XPLM_VK_S => Key::S,
+        sys::XPLM_VK_T => Key::T,
+        sys::XPLM_VK_U => Key::U,
+        sys::XPLM_VK_V => Key::V,
+        sys::XPLM_VK_W => Key::W,
+        sys::XPLM_VK_X => Key::X,
+        sys::XPLM_VK_Y => Key::Y,
+        sys::XPLM_VK_Z => Key::Z,
+        sys::XPLM_VK_NUMPAD0 => Key::Numpad0,
+        sys::XPLM_VK_NUMPAD1 => Key::Numpad1,
+        sys::XPLM_VK_NUMPAD2 => Key::Numpad2,
+        sys::XPLM_VK_NUMPAD3 => Key::Numpad3,
+        sys::XPLM_VK_NUMPAD4 => Key::Numpad4,
+        sys::XPLM_VK_NUMPAD5 => Key::Numpad5,
+        sys::XPLM_VK_NUMPAD6 => Key::Numpad6,
+        sys::XPLM_VK_NUMPAD7 => Key::Numpad7,
+        sys::XPLM_VK_NUMPAD8 => Key::Numpad8,
+        sys::XPLM_VK_NUMPAD9 => Key::Numpad9,
+        sys::XPLM_VK_MULTIPLY => Key::Multiply,
+        sys::XPLM_VK_ADD => Key::Add,
+        sys::XPLM_VK_SEPARATOR => Key::Separator,
+        sys::XPLM_VK_SUBTRACT => Key::Subtract,
+        sys::XPLM_VK_DECIMAL => Key::Decimal,
+        sys::XPLM_VK_DIVIDE => Key::Divide,
+        sys::XPLM_VK_F1 => Key::F1,
+        sys::XPLM_VK_F2 => Key::F2,
+        sys::XPLM_VK_F3 => Key::F3,
+        sys::XPLM_VK_F4 => Key::F4,
+        sys::XPLM_VK_F5 => Key::F5,
+        sys::XPLM_VK_F6 => Key::F6,
+        sys::XPLM_VK_F7 => Key::F7,
+        sys::XPLM_VK_F8 => Key::F8,
+        sys::XPLM_VK_F9 => Key::F9,
+        sys::XPLM_VK_F10 => Key::F10,
+        sys::XPLM_VK_F11 => Key::F11,
+        sys::XPLM_VK_F12 => Key::F12,
+        sys::XPLM_VK_F13 => Key::F13,
+        sys::XPLM_VK_F14 => Key::F14,
+        sys::XPLM_VK_F15 => Key::F15,
+        sys::XPLM_VK_F16 => Key::F16,
+        sys::XPLM_VK_F17 => Key::F17,
+        sys::XPLM_VK_F18 => Key::F18,
+        sys::XPLM_VK_F19 => Key::F19,
+        sys::XPLM_VK_F20 => Key::F20,
+        sys::XPLM_VK_F21 => Key::F21,
+        sys::XPLM_VK_F22 => Key::F22,
+        sys::XPLM_VK_F23 => Key::F23,
+        sys::XPLM_VK_F24 => Key::F24,
+        sys::XPLM_VK_EQUAL => Key::Equal,
+        sys::XPLM_VK_MINUS => Key::Minus,
+        sys::XPLM_VK_RBRACE => Key::ClosingBrace,
+        sys::XPLM_VK_LBRACE => Key::OpeningBrace,
+        sys::XPLM_VK_QUOTE => Key::Quote,
+        sys::XPLM_VK_SEMICOLON => Key::Semicolon,
+        sys::XPLM_VK_BACKSLASH => Key::Backslash,
+        sys::XPLM_VK_COMMA => Key::Comma,
+        sys::XPLM_VK_SLASH => Key::Slash,
+        sys::XPLM_VK_PERIOD => Key::Period,
+        sys::XPLM_VK_BACKQUOTE => Key::Backquote,
+        sys::XPLM_VK_ENTER => Key::Enter,
+        sys::XPLM_VK_NUMPAD_ENT => Key::NumpadEnter,
+        sys::XPLM_VK_NUMPAD_EQ => Key::NumpadEqual,
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flags(flags: &[u32]) -> xplm_sys::XPLMKeyFlags {
+        flags.iter().fold(0, |all, flag| all | *flag as xplm_sys::XPLMKeyFlags)
+    }
+
+    #[test]
+    fn key_callback_arguments() {
+        let down = flags(&[xplm_sys::xplm_DownFlag, xplm_sys::xplm_ShiftFlag]);
+        let (key, char, modifiers, pressed) =
+            key_event(b'A' as c_char, down, xplm_sys::XPLM_VK_A as c_char).unwrap();
+        assert_eq!((key, char, pressed), (Key::A, Some('A'), true));
+        assert_eq!(modifiers, Modifiers { shift: true, ..Default::default() });
+
+        let up = flags(&[xplm_sys::xplm_UpFlag, xplm_sys::xplm_ControlFlag]);
+        let (key, char, modifiers, pressed) =
+            key_event(b'\r' as c_char, up, xplm_sys::XPLM_VK_RETURN as c_char).unwrap();
+        assert_eq!((key, char, pressed), (Key::Return, None, false));
+        assert!(modifiers.ctrl && modifiers.command, "{modifiers:?}");
+
+        // Codes above 127 are negative as a c_char
+        let (key, ..) = key_event(0, down, xplm_sys::XPLM_VK_NUMPAD_EQ as u8 as c_char).unwrap();
+        assert_eq!(key, Key::NumpadEqual);
+    }
+
+    #[test]
+    fn key_callbacks_without_press_or_known_key_are_dropped() {
+        let down = flags(&[xplm_sys::xplm_DownFlag]);
+        assert!(key_event(b'a' as c_char, 0, xplm_sys::XPLM_VK_A as c_char).is_none());
+        assert!(key_event(0, down, 0x07).is_none());
+    }
+
+    #[test]
+    fn mouse_status() {
+        let action = |status: u32| mouse_action(status as xplm_sys::XPLMMouseStatus);
+        assert!(matches!(action(xplm_sys::xplm_MouseDown), Some(MouseAction::Down)));
+        assert!(matches!(action(xplm_sys::xplm_MouseDrag), Some(MouseAction::Drag)));
+        assert!(matches!(action(xplm_sys::xplm_MouseUp), Some(MouseAction::Up)));
+        assert!(action(42).is_none());
+    }
+}
@@ -0,0 +1,70 @@
+//! Compares the painter output against the images in `golden/`.
+//!
+//! Regenerate them with `cargo run --example golden --features offscreen -- --update`.
+
+#![cfg(feature = "offscreen")]
+
+use std::path::Path;
+
+use xplm_egui::{
+    offscreen::{self, GoldenResult, OffscreenRenderer, GOLDEN_SIZE, GOLDEN_TOLERANCE},
+    ColorPipeline,
+};
+
+#[test]
+fn canned_scenes_match_the_golden_images() {
+    let golden_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden");
+    let mut failures = Vec::new();
+    for check in offscreen::check_goldens(&golden_dir, false).unwrap() {
+        if !matches!(check.result, GoldenResult::Match(_)) {
+            check.save_actual().unwrap();
+            failures.push(format!("{}: {:?}", check.path.display(), check.result));
+        }
+    }
+    assert!(failures.is_empty(), "{failures:#?}");
+}
+
+#[test]
+fn golden_images_of_another_size_are_a_mismatch() {
+    let path = std::env::temp_dir().join(format!("xplm-egui-golden-{}.png", std::process::id()));
+    image::RgbaImage::new(2, 2).save(&path).unwrap();
+    let actual = image::RgbaImage::new(GOLDEN_SIZE, GOLDEN_SIZE);
+    let result = offscreen::check_golden(&path, &actual, GOLDEN_TOLERANCE, false);
+    std::fs::remove_file(&path).unwrap();
+    assert!(
+        matches!(result, Ok(GoldenResult::SizeMismatch { expected: (2, 2) })),
+        "{result:?}"
+    );
+}
+
+/// Guards the golden images themselves: a vertex color gradient from black to white must be
+/// interpolated in sRGB by the gamma space pipeline, and in linear light by the linear one.
+#[test]
+fn pipelines_interpolate_in_their_color_space() {
+    let (_, gradient) = offscreen::canned_scenes()
+        .into_iter()
+        .find(|(name, _)| *name == "gradient")
+        .unwrap();
+    // The center of the pixel is at 32.5 / 64 of the gradient
+    let t = 32.5 / 64.0;
+    for (pipeline, expected) in [
+        (ColorPipeline::GammaSpace, t * 255.0),
+        (ColorPipeline::Linear, encode_srgb(t) * 255.0),
+    ] {
+        let mut renderer = OffscreenRenderer::new(GOLDEN_SIZE, GOLDEN_SIZE, pipeline).unwrap();
+        let image = renderer.render(1.0, &gradient);
+        let actual = image.get_pixel(32, 32)[0];
+        assert!(
+            (actual as f32 - expected).abs() <= GOLDEN_TOLERANCE as f32,
+            "{pipeline:?}: {actual}, expected {expected}"
+        );
+    }
+}
+
+fn encode_srgb(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
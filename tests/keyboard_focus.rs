@@ -0,0 +1,70 @@
+//! Checks that the window only holds X-Plane's keyboard focus while egui wants key presses.
+
+#![cfg(feature = "offscreen")]
+
+use xplm::{
+    geometry::{Point, Rect},
+    window::{Key, MouseAction},
+};
+use xplm_egui::{
+    mock::{MockBackend, MockWindow},
+    offscreen::OffscreenContext,
+    RenderOptions, XplmGuiContext,
+};
+
+const SIZE: u32 = 200;
+
+fn click(context: &mut XplmGuiContext, window: &MockWindow, position: Point<i32>) {
+    for action in [MouseAction::Down, MouseAction::Up] {
+        context
+            .input_state
+            .mouse_input(window, position, action, egui::PointerButton::Primary);
+    }
+}
+
+#[test]
+fn text_edits_take_and_release_the_keyboard_focus() {
+    let offscreen = OffscreenContext::new(SIZE, SIZE, false).unwrap();
+    let mut context = XplmGuiContext::with_backend(
+        offscreen.load_gl(),
+        MockBackend::default(),
+        RenderOptions::default(),
+    )
+    .unwrap();
+    let window = MockWindow::new(Rect::from_left_top_right_bottom(
+        0,
+        SIZE as i32,
+        SIZE as i32,
+        0,
+    ));
+    let mut text = String::new();
+    let mut frame = |context: &mut XplmGuiContext| {
+        // X-Plane asks for the cursor every frame, which also tells egui the window size
+        context.input_state.cursor(&window, Point::from((0, 0)));
+        context.update(&window, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| ui.text_edit_singleline(&mut text));
+        });
+        context.draw(&window);
+    };
+
+    frame(&mut context);
+    assert!(!window.has_keyboard_focus());
+
+    // The text edit is the first widget in the top left corner of the panel
+    click(&mut context, &window, Point::from((20, SIZE as i32 - 15)));
+    frame(&mut context);
+    assert!(window.has_keyboard_focus());
+
+    context
+        .input_state
+        .key_input(Key::A, Some('a'), Default::default(), true);
+    frame(&mut context);
+    assert!(window.has_keyboard_focus());
+
+    context
+        .input_state
+        .key_input(Key::Escape, None, Default::default(), true);
+    frame(&mut context);
+    assert!(!window.has_keyboard_focus());
+    assert_eq!(text, "a");
+}
@@ -0,0 +1,100 @@
+//! Records the input of a few frames and checks that replaying it leads to the same ui state.
+
+#![cfg(all(feature = "offscreen", feature = "recording"))]
+
+use xplm::{
+    geometry::{Point, Rect},
+    window::{Key, MouseAction},
+};
+use xplm_egui::{
+    mock::{MockBackend, MockWindow},
+    offscreen::OffscreenContext,
+    recording::{InputReplay, RawEvent},
+    RenderOptions, XplmGuiContext,
+};
+
+const SIZE: u32 = 200;
+
+fn mock_context(offscreen: &OffscreenContext) -> XplmGuiContext {
+    XplmGuiContext::with_backend(
+        offscreen.load_gl(),
+        MockBackend::default(),
+        RenderOptions::default(),
+    )
+    .unwrap()
+}
+
+fn text_edit(text: &mut String) -> impl FnOnce(&egui::CtxRef) + '_ {
+    |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| ui.text_edit_singleline(text));
+    }
+}
+
+#[test]
+fn replaying_a_recording_restores_the_ui_state() {
+    let offscreen = OffscreenContext::new(SIZE, SIZE, false).unwrap();
+    let window = MockWindow::new(Rect::from_left_top_right_bottom(
+        0,
+        SIZE as i32,
+        SIZE as i32,
+        0,
+    ));
+    let path = std::env::temp_dir().join(format!(
+        "xplm-egui-recording-{}.jsonl",
+        std::process::id()
+    ));
+
+    let mut recorded_text = String::new();
+    let mut context = mock_context(&offscreen);
+    context.input_state.start_recording(&path).unwrap();
+    let mut frame = |context: &mut XplmGuiContext, input: &dyn Fn(&mut XplmGuiContext)| {
+        // X-Plane asks for the cursor every frame, which also tells egui the window size
+        context.input_state.cursor(&window, Point::from((0, 0)));
+        input(context);
+        context.update(&window, text_edit(&mut recorded_text));
+    };
+    frame(&mut context, &|_| ());
+    // The text edit is the first widget in the top left corner of the panel
+    frame(&mut context, &|context| {
+        for action in [MouseAction::Down, MouseAction::Up] {
+            context.input_state.mouse_input(
+                &window,
+                Point::from((20, SIZE as i32 - 15)),
+                action,
+                egui::PointerButton::Primary,
+            );
+        }
+    });
+    frame(&mut context, &|context| {
+        context
+            .input_state
+            .key_input(Key::H, Some('h'), Default::default(), true);
+        context
+            .input_state
+            .key_input(Key::I, Some('i'), Default::default(), true);
+    });
+    context.input_state.stop_recording();
+    assert_eq!(recorded_text, "hi");
+
+    let mut replay = InputReplay::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(replay.remaining(), 3);
+    let frames = replay.frames();
+    assert!(frames[1].raw_events.iter().any(|event| matches!(
+        event,
+        RawEvent::Mouse { x: 20, button: egui::PointerButton::Primary, .. }
+    )));
+    assert!(matches!(
+        &frames[2].raw_events[..],
+        [
+            RawEvent::Key { key: h, char: Some('h'), pressed: true, .. },
+            RawEvent::Key { key: i, char: Some('i'), pressed: true, .. },
+        ] if h == "H" && i == "I"
+    ), "{:?}", frames[2].raw_events);
+
+    // A fresh context gets nothing but the recorded input
+    let mut replayed_text = String::new();
+    let mut context = mock_context(&offscreen);
+    while context.replay_frame(&window, &mut replay, text_edit(&mut replayed_text)) {}
+    assert_eq!(replayed_text, recorded_text);
+}
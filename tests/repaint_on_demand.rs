@@ -0,0 +1,90 @@
+//! Checks that repaint on demand only runs the ui when something happened, but keeps drawing.
+
+#![cfg(feature = "offscreen")]
+
+use std::time::Duration;
+
+use xplm::geometry::{Point, Rect};
+use xplm_egui::{
+    mock::{MockBackend, MockWindow},
+    offscreen::OffscreenContext,
+    FrameCounters, RenderOptions, XplmGuiContext,
+};
+
+const SIZE: u32 = 200;
+
+/// Updates and draws like X-Plane's draw callback would, returning whether the ui ran.
+fn frame(context: &mut XplmGuiContext, window: &MockWindow, cursor: Point<i32>) -> bool {
+    let before = context.frame_counters();
+    // X-Plane asks for the cursor every frame, which also tells egui the window size
+    context.input_state.cursor(window, cursor);
+    context.update(window, |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| ui.label("Nothing changes here"));
+    });
+    context.draw(window);
+    let after = context.frame_counters();
+    assert_eq!(
+        after.executed + after.skipped,
+        before.executed + before.skipped + 1
+    );
+    after.executed > before.executed
+}
+
+/// Runs frames until one is skipped, as egui itself asks for a few more frames e.g. after
+/// starting or after the mouse moved.
+fn settle(context: &mut XplmGuiContext, window: &MockWindow, cursor: Point<i32>) {
+    let settled = (0..5).any(|_| !frame(context, window, cursor));
+    assert!(settled, "{:?}", context.frame_counters());
+}
+
+#[test]
+fn frames_only_run_on_input_and_repaint_requests() {
+    let offscreen = OffscreenContext::new(SIZE, SIZE, false).unwrap();
+    let backend = MockBackend::default();
+    let mut context = XplmGuiContext::with_backend(
+        offscreen.load_gl(),
+        backend.clone(),
+        RenderOptions::default(),
+    )
+    .unwrap();
+    context.set_repaint_on_demand(true);
+    let window = MockWindow::new(Rect::from_left_top_right_bottom(
+        0,
+        SIZE as i32,
+        SIZE as i32,
+        0,
+    ));
+    let cursor = Point::from((10, 10));
+
+    settle(&mut context, &window, cursor);
+
+    // Without input, the meshes of the last frame are drawn again
+    let counters = context.frame_counters();
+    let draws = backend.graphics_state_calls();
+    for _ in 0..3 {
+        assert!(!frame(&mut context, &window, cursor));
+    }
+    assert_eq!(
+        context.frame_counters(),
+        FrameCounters {
+            executed: counters.executed,
+            skipped: counters.skipped + 3,
+        }
+    );
+    assert_eq!(backend.graphics_state_calls(), draws + 3);
+
+    // Moving the mouse is input
+    let cursor = Point::from((20, 20));
+    assert!(frame(&mut context, &window, cursor));
+    settle(&mut context, &window, cursor);
+
+    context.request_repaint();
+    assert!(frame(&mut context, &window, cursor));
+    settle(&mut context, &window, cursor);
+
+    context.request_repaint_after(Duration::from_millis(50));
+    assert!(!frame(&mut context, &window, cursor));
+    std::thread::sleep(Duration::from_millis(60));
+    assert!(frame(&mut context, &window, cursor));
+    assert!(!frame(&mut context, &window, cursor));
+}